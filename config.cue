@@ -1,5 +1,5 @@
 package budgetConfig
 
-resolution_date: null
-showAllRows:     true
+resolutionDate: null
+showAllRows:    true
 outputFormat: {"visual_output": "report.html"}
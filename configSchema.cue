@@ -1,8 +1,74 @@
 package budgetConfig
 
-budgetName:          string
-personalAccessToken: string
-categoryGroupWatchList: {[string]: =~"^#[0-9a-fA-F]{6}$"}
-resolution_date: null | (string & =~"^\\d{4}-\\d{2}-\\d{2}$")
-showAllRows:     bool
-outputFormat: "polars_print" | "csv_print" | {csv_output: string, visual_output?: _|_} | {visual_output: string, csv_output?: _|_}
+// Keys are group names (ignoring case and emoji), or "id:<group id>", "glob:<pattern>" or
+// "re:<regex>".
+#WatchList: {[string]: =~"^#[0-9a-fA-F]{6}$"}
+
+// Keyed like #WatchList. Per category group: categories to leave out of a watched group,
+// categories to show from an unwatched one, display names, and categories to list first.
+#CategoryRules: {[string]: {
+	exclude?: [...string]
+	include?: [...string]
+	aliases?: [string]: string
+	order?: [...string]
+}}
+
+// The token itself, or where to read it from. When several sources are set the first of
+// env (if the variable is set), file, command wins, then personalAccessToken.
+personalAccessToken?: string
+personalAccessTokenFrom?: {
+	env?:  string
+	file?: string
+	command?: [string, ...string]
+}
+// Either a single budget...
+budgetName?:             string
+categoryGroupWatchList?: #WatchList
+categoryRules?:          #CategoryRules
+// ...or several, each reported in its own section.
+budgets?: [...{
+	budgetName:             string
+	categoryGroupWatchList: #WatchList
+	categoryRules?:         #CategoryRules
+}]
+resolutionDate: null | (string & =~"^\\d{4}-\\d{2}-\\d{2}$")
+showAllRows:    bool
+#Date: string & =~"^\\d{4}-\\d{2}-\\d{2}$"
+// The days to report on around the resolution date; monthWeek if unset.
+#Period: "monthWeek" | "month" | "quarter" | "year" | {range: {start: #Date, end: #Date}} | {rollingDays: int & >=1}
+period?: #Period
+#Weekday: "Mon" | "Tue" | "Wed" | "Thu" | "Fri" | "Sat" | "Sun" | "Monday" | "Tuesday" | "Wednesday" | "Thursday" | "Friday" | "Saturday" | "Sunday"
+// The day weeks start on; Sunday if unset.
+weekStart?: #Weekday
+// "year" counts weeks from the one containing 1 January; "iso" uses ISO 8601 weeks, which
+// start on Monday whatever weekStart says.
+weekNumbering?: "year" | "iso"
+showNetWorth?:   bool
+#Output: "polars_print" | "csv_print" | {csv_output: string, visual_output?: _|_} | {visual_output: string, csv_output?: _|_}
+// One output, or a list of them rendered from the same data.
+outputFormat: #Output | [#Output, ...#Output]
+currencyFormat?: {
+	currencySymbol?:   string
+	symbolFirst?:      bool
+	displaySymbol?:    bool
+	decimalDigits?:    int & >=0
+	decimalSeparator?: string
+	groupSeparator?:   string
+}
+
+// Named variations picked with --profile, each merged over the settings above. A profile's
+// categoryGroupWatchList and categoryRules replace the shared ones rather than merging.
+profiles?: [string]: {
+	budgetName?:             string
+	categoryGroupWatchList?: #WatchList
+	categoryRules?:          #CategoryRules
+	budgets?: [...{...}]
+	resolutionDate?:  null | (string & =~"^\\d{4}-\\d{2}-\\d{2}$")
+	period?:          #Period
+	weekStart?:       #Weekday
+	weekNumbering?:   "year" | "iso"
+	showAllRows?:     bool
+	showNetWorth?:    bool
+	outputFormat?:    #Output | [#Output, ...#Output]
+	currencyFormat?: {...}
+}
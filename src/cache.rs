@@ -0,0 +1,467 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{Datelike, NaiveDate};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::error::{Error, Result, ResultExt};
+use crate::ynab::{
+    Account, BudgetSummary, Category, CategoryGroup, Delta, MonthDetail, ScheduledTransaction,
+    Transaction, YnabApi,
+};
+
+/// How long the cached budget list is trusted before it is refetched. YNAB has no
+/// delta request for budgets, and they are rarely added or renamed.
+pub const BUDGETS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+// --- On-disk layout ---
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedCategoryGroups {
+    server_knowledge: i64,
+    category_groups: Vec<CategoryGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedTransactions {
+    server_knowledge: i64,
+    since_date: NaiveDate,
+    transactions: Vec<Transaction>,
+}
+
+/// Month data has no delta request, so each entry remembers the server knowledge it was
+/// fetched at and is only reused while the budget has not changed since.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedAtKnowledge<T> {
+    server_knowledge: i64,
+    value: T,
+}
+
+// --- Caching client ---
+
+/// A [`YnabApi`] that keeps a copy of YNAB data on disk, one directory per budget, and
+/// uses `last_knowledge_of_server` delta requests so repeat runs only pull changes.
+pub struct CachingYnabApi<A> {
+    inner: A,
+    cache_dir: PathBuf,
+    budgets_max_age: Duration,
+    /// Latest server knowledge seen this session, per budget.
+    knowledge: RefCell<HashMap<String, i64>>,
+    /// Cache files that could not be read and were fetched again, for [`Self::take_warnings`].
+    warnings: RefCell<Vec<String>>,
+}
+
+impl<A: YnabApi> CachingYnabApi<A> {
+    pub fn new(inner: A, cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            cache_dir: cache_dir.into(),
+            budgets_max_age: BUDGETS_MAX_AGE,
+            knowledge: RefCell::new(HashMap::new()),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn with_budgets_max_age(mut self, max_age: Duration) -> Self {
+        self.budgets_max_age = max_age;
+        self
+    }
+
+    /// Problems met since the last call, such as unreadable cache files that were fetched
+    /// again. They do not stop a run, so they are left to the caller to report.
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    fn read_cached<T: DeserializeOwned>(&self, path: &Path) -> Option<T> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.warnings
+                    .borrow_mut()
+                    .push(format!("ignoring unreadable cache file {path:?}: {err}"));
+                None
+            }
+        }
+    }
+
+    fn budget_dir(&self, budget_id: &str) -> PathBuf {
+        self.cache_dir.join(budget_id)
+    }
+
+    fn record_knowledge(&self, budget_id: &str, server_knowledge: i64) {
+        let mut knowledge = self.knowledge.borrow_mut();
+        let entry = knowledge.entry(budget_id.to_string()).or_insert(i64::MIN);
+        *entry = (*entry).max(server_knowledge);
+    }
+
+    fn current_knowledge(&self, budget_id: &str) -> Option<i64> {
+        self.knowledge.borrow().get(budget_id).copied()
+    }
+
+    fn month_path(&self, budget_id: &str, month: NaiveDate) -> PathBuf {
+        self.budget_dir(budget_id).join("months").join(format!(
+            "{:04}-{:02}.json",
+            month.year(),
+            month.month()
+        ))
+    }
+
+    fn month_category_path(&self, budget_id: &str, month: NaiveDate, category_id: &str) -> PathBuf {
+        self.budget_dir(budget_id).join("months").join(format!(
+            "{:04}-{:02}_{category_id}.json",
+            month.year(),
+            month.month()
+        ))
+    }
+
+    /// The entry at `path`, if it was stored at the server knowledge seen this session.
+    /// Nothing is reused before a delta request has told us the budget's current knowledge.
+    fn read_at_knowledge<T: DeserializeOwned>(&self, budget_id: &str, path: &Path) -> Option<T> {
+        let knowledge = self.current_knowledge(budget_id)?;
+        self.read_cached::<CachedAtKnowledge<T>>(path)
+            .filter(|cached| cached.server_knowledge == knowledge)
+            .map(|cached| cached.value)
+    }
+
+    fn write_at_knowledge<T: Serialize>(
+        &self,
+        budget_id: &str,
+        path: &Path,
+        value: &T,
+    ) -> Result<()> {
+        match self.current_knowledge(budget_id) {
+            Some(server_knowledge) => write_cached(
+                path,
+                &CachedAtKnowledge {
+                    server_knowledge,
+                    value,
+                },
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn fetch_at_knowledge<T: Serialize + DeserializeOwned>(
+        &self,
+        budget_id: &str,
+        path: &Path,
+        fetch: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if let Some(value) = self.read_at_knowledge(budget_id, path) {
+            return Ok(value);
+        }
+        let value = fetch()?;
+        self.write_at_knowledge(budget_id, path, &value)?;
+        Ok(value)
+    }
+}
+
+fn write_cached<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| Error::io(format!("creating cache directory {parent:?}"), err))?;
+    }
+    let contents = serde_json::to_string(value).shape_context("serializing cache entry")?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, contents)
+        .map_err(|err| Error::io(format!("writing {tmp_path:?}"), err))?;
+    std::fs::rename(&tmp_path, path).map_err(|err| Error::io(format!("replacing {path:?}"), err))
+}
+
+fn is_fresh(path: &Path, max_age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < max_age)
+}
+
+/// Applies a category group delta on top of cached groups: changed groups and categories
+/// replace their cached versions by id, deleted ones are dropped.
+pub fn merge_category_groups(
+    cached: Vec<CategoryGroup>,
+    changes: Vec<CategoryGroup>,
+) -> Vec<CategoryGroup> {
+    let mut groups: IndexMap<String, CategoryGroup> =
+        cached.into_iter().map(|g| (g.id.clone(), g)).collect();
+
+    for change in changes {
+        if change.deleted {
+            groups.shift_remove(&change.id);
+            continue;
+        }
+        let cached_categories = groups
+            .get(&change.id)
+            .map(|g| g.categories.clone())
+            .unwrap_or_default();
+        let mut categories: IndexMap<String, Category> = cached_categories
+            .into_iter()
+            .map(|c| (c.id.clone(), c))
+            .collect();
+        for category in &change.categories {
+            if category.deleted {
+                categories.shift_remove(&category.id);
+            } else {
+                categories.insert(category.id.clone(), category.clone());
+            }
+        }
+        groups.insert(
+            change.id.clone(),
+            CategoryGroup {
+                categories: categories.into_values().collect(),
+                ..change
+            },
+        );
+    }
+
+    groups.into_values().collect()
+}
+
+/// Applies a transaction delta on top of cached transactions: changed transactions
+/// replace their cached versions by id, deleted ones are dropped.
+pub fn merge_transactions(cached: Vec<Transaction>, changes: Vec<Transaction>) -> Vec<Transaction> {
+    let mut transactions: IndexMap<String, Transaction> =
+        cached.into_iter().map(|t| (t.id.clone(), t)).collect();
+
+    for change in changes {
+        if change.deleted {
+            transactions.shift_remove(&change.id);
+        } else {
+            transactions.insert(change.id.clone(), change);
+        }
+    }
+
+    let mut merged: Vec<Transaction> = transactions.into_values().collect();
+    merged.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+    merged
+}
+
+impl<A: YnabApi> YnabApi for CachingYnabApi<A> {
+    fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        let path = self.cache_dir.join("budgets.json");
+        if is_fresh(&path, self.budgets_max_age)
+            && let Some(budgets) = self.read_cached(&path)
+        {
+            return Ok(budgets);
+        }
+        let budgets = self.inner.get_budgets()?;
+        write_cached(&path, &budgets)?;
+        Ok(budgets)
+    }
+
+    fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
+        Ok(self.get_category_groups_delta(budget_id, None)?.items)
+    }
+
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        let path = self.budget_dir(budget_id).join("accounts.json");
+        self.fetch_at_knowledge(budget_id, &path, || self.inner.get_accounts(budget_id))
+    }
+
+    fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        let path = self.month_category_path(budget_id, month, category_id);
+        self.fetch_at_knowledge(budget_id, &path, || {
+            self.inner.get_month_category(budget_id, month, category_id)
+        })
+    }
+
+    fn get_month_categories_by_id(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_ids: &[&str],
+    ) -> Result<Vec<Category>> {
+        let cached: Vec<Option<Category>> = category_ids
+            .iter()
+            .map(|id| {
+                let path = self.month_category_path(budget_id, month, id);
+                self.read_at_knowledge(budget_id, &path)
+            })
+            .collect();
+        let missing_ids: Vec<&str> = category_ids
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut fetched = self
+            .inner
+            .get_month_categories_by_id(budget_id, month, &missing_ids)?
+            .into_iter();
+        category_ids
+            .iter()
+            .zip(cached)
+            .map(|(id, cached)| match cached {
+                Some(category) => Ok(category),
+                None => {
+                    let category = fetched.next().ok_or_else(|| {
+                        Error::DataShape(format!("no month values returned for category {id}"))
+                    })?;
+                    let path = self.month_category_path(budget_id, month, id);
+                    self.write_at_knowledge(budget_id, &path, &category)?;
+                    Ok(category)
+                }
+            })
+            .collect()
+    }
+
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        let path = self.month_path(budget_id, month);
+        self.fetch_at_knowledge(budget_id, &path, || {
+            self.inner.get_month_detail(budget_id, month)
+        })
+    }
+
+    fn get_month_details(&self, budget_id: &str, months: &[NaiveDate]) -> Result<Vec<MonthDetail>> {
+        let cached: Vec<Option<MonthDetail>> = months
+            .iter()
+            .map(|month| self.read_at_knowledge(budget_id, &self.month_path(budget_id, *month)))
+            .collect();
+        let missing: Vec<NaiveDate> = months
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(month, _)| *month)
+            .collect();
+
+        let mut fetched = self
+            .inner
+            .get_month_details(budget_id, &missing)?
+            .into_iter();
+        months
+            .iter()
+            .zip(cached)
+            .map(|(month, cached)| match cached {
+                Some(detail) => Ok(detail),
+                None => {
+                    let detail = fetched.next().ok_or_else(|| {
+                        Error::DataShape(format!("no month returned for {month}"))
+                    })?;
+                    self.write_at_knowledge(
+                        budget_id,
+                        &self.month_path(budget_id, *month),
+                        &detail,
+                    )?;
+                    Ok(detail)
+                }
+            })
+            .collect()
+    }
+
+    fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
+        Ok(self
+            .get_transactions_delta(budget_id, since_date, None)?
+            .items)
+    }
+
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        let path = self
+            .budget_dir(budget_id)
+            .join("scheduled_transactions.json");
+        self.fetch_at_knowledge(budget_id, &path, || {
+            self.inner.get_scheduled_transactions(budget_id)
+        })
+    }
+
+    /// Serves the full cached set of category groups after applying any changes since the
+    /// cached server knowledge. `last_knowledge_of_server` from the caller is ignored; the
+    /// returned delta always holds every live group.
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        _last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        let path = self.budget_dir(budget_id).join("category_groups.json");
+        let cached = self.read_cached::<CachedCategoryGroups>(&path);
+
+        let updated = match cached {
+            Some(cached) => {
+                let delta = self
+                    .inner
+                    .get_category_groups_delta(budget_id, Some(cached.server_knowledge))?;
+                CachedCategoryGroups {
+                    server_knowledge: delta.server_knowledge,
+                    category_groups: merge_category_groups(cached.category_groups, delta.items),
+                }
+            }
+            None => {
+                let full = self.inner.get_category_groups_delta(budget_id, None)?;
+                CachedCategoryGroups {
+                    server_knowledge: full.server_knowledge,
+                    category_groups: merge_category_groups(Vec::new(), full.items),
+                }
+            }
+        };
+
+        write_cached(&path, &updated)?;
+        self.record_knowledge(budget_id, updated.server_knowledge);
+        Ok(Delta {
+            items: updated.category_groups,
+            server_knowledge: updated.server_knowledge,
+        })
+    }
+
+    /// Serves every live cached transaction on or after `since_date` after applying any
+    /// changes since the cached server knowledge. A `since_date` earlier than the cached one
+    /// forces a full refetch, since the cache cannot contain the older transactions.
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        _last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        let path = self.budget_dir(budget_id).join("transactions.json");
+        let cached = self
+            .read_cached::<CachedTransactions>(&path)
+            .filter(|cached| cached.since_date <= since_date);
+
+        let (server_knowledge, transactions) = match cached {
+            Some(cached) => {
+                let delta = self.inner.get_transactions_delta(
+                    budget_id,
+                    since_date,
+                    Some(cached.server_knowledge),
+                )?;
+                (
+                    delta.server_knowledge,
+                    merge_transactions(cached.transactions, delta.items),
+                )
+            }
+            None => {
+                let full = self
+                    .inner
+                    .get_transactions_delta(budget_id, since_date, None)?;
+                (
+                    full.server_knowledge,
+                    merge_transactions(Vec::new(), full.items),
+                )
+            }
+        };
+
+        let updated = CachedTransactions {
+            server_knowledge,
+            since_date,
+            transactions: transactions
+                .into_iter()
+                .filter(|t| t.date >= since_date)
+                .collect(),
+        };
+        write_cached(&path, &updated)?;
+        self.record_knowledge(budget_id, updated.server_knowledge);
+        Ok(Delta {
+            items: updated.transactions,
+            server_knowledge: updated.server_knowledge,
+        })
+    }
+}
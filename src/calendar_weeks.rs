@@ -1,4 +1,7 @@
-use chrono::{Datelike, Duration, NaiveDate};
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MonthWeek {
@@ -15,11 +18,45 @@ impl MonthWeek {
             .map(|offset| self.week_start + Duration::days(offset))
             .collect()
     }
+
+    /// The week's number and the year it is counted in, as headers show them.
+    pub fn week_of_year(&self, numbering: WeekNumbering) -> (usize, i32) {
+        match numbering {
+            WeekNumbering::Year => (self.week_number, self.week_start.year()),
+            WeekNumbering::Iso => {
+                let iso = self.week_start.iso_week();
+                (iso.week() as usize, iso.year())
+            }
+        }
+    }
 }
 
-fn previous_sunday(day: NaiveDate) -> NaiveDate {
-    let days_since_sunday = (day.weekday().num_days_from_sunday()) as i64;
-    day - Duration::days(days_since_sunday)
+/// How weeks are numbered in report headers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekNumbering {
+    /// Week 1 is the week containing 1 January, whatever day weeks start on.
+    #[default]
+    Year,
+    /// ISO 8601: weeks start on Monday and week 1 contains the year's first Thursday, so
+    /// days at either end of December and January can belong to the neighbouring year.
+    Iso,
+}
+
+impl WeekNumbering {
+    /// The day weeks start on: Monday for ISO weeks, `configured` otherwise.
+    pub fn week_start(self, configured: Weekday) -> Weekday {
+        match self {
+            Self::Year => configured,
+            Self::Iso => Weekday::Mon,
+        }
+    }
+}
+
+/// The last `week_start` day on or before `day`.
+fn previous_week_start(day: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let days_since_start = day.weekday().days_since(week_start) as i64;
+    day - Duration::days(days_since_start)
 }
 
 fn week_days(week_start: NaiveDate) -> Vec<NaiveDate> {
@@ -35,6 +72,17 @@ fn split_by_month(days: &[NaiveDate]) -> Vec<u32> {
     months
 }
 
+fn last_day_of_month(year: i32, month: u32) -> NaiveDate {
+    if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .expect("valid next month")
+    .pred_opt()
+    .expect("valid last day")
+}
+
 fn make_month_week(
     year: i32,
     month: u32,
@@ -43,16 +91,7 @@ fn make_month_week(
     week_number: usize,
 ) -> MonthWeek {
     let month_first = NaiveDate::from_ymd_opt(year, month, 1).expect("valid month start");
-    let days_in_month = if month == 12 {
-        NaiveDate::from_ymd_opt(year + 1, 1, 1)
-    } else {
-        NaiveDate::from_ymd_opt(year, month + 1, 1)
-    }
-    .expect("valid next month")
-    .pred_opt()
-    .expect("valid last day")
-    .day();
-    let month_last = NaiveDate::from_ymd_opt(year, month, days_in_month).expect("valid month end");
+    let month_last = last_day_of_month(year, month);
 
     MonthWeek {
         month,
@@ -62,11 +101,14 @@ fn make_month_week(
     }
 }
 
-pub fn partition_year_into_month_weeks(year: i32) -> Vec<MonthWeek> {
+/// Splits `year` into weeks starting on `week_start`, cut in two wherever a month ends.
+/// Weeks are numbered from 1 for the week containing 1 January; both halves of a split week
+/// share its number.
+pub fn partition_year_into_month_weeks(year: i32, week_start: Weekday) -> Vec<MonthWeek> {
     let first_day = NaiveDate::from_ymd_opt(year, 1, 1).expect("valid year start");
     let last_day = NaiveDate::from_ymd_opt(year, 12, 31).expect("valid year end");
-    let anchor_week_start = previous_sunday(first_day);
-    let last_week_end = previous_sunday(last_day) + Duration::days(6);
+    let anchor_week_start = previous_week_start(first_day, week_start);
+    let last_week_end = previous_week_start(last_day, week_start) + Duration::days(6);
     let num_weeks = ((last_week_end - anchor_week_start).num_days() / 7) + 1;
 
     let mut result = Vec::new();
@@ -91,23 +133,159 @@ pub fn partition_year_into_month_weeks(year: i32) -> Vec<MonthWeek> {
     result
 }
 
-pub fn month_weeks(year: i32, month: u32) -> Vec<MonthWeek> {
-    partition_year_into_month_weeks(year)
+pub fn month_weeks(year: i32, month: u32, week_start: Weekday) -> Vec<MonthWeek> {
+    partition_year_into_month_weeks(year, week_start)
         .into_iter()
         .filter(|w| w.month == month)
         .collect()
 }
 
-pub fn month_week_for_date(day: NaiveDate) -> anyhow::Result<MonthWeek> {
-    month_weeks(day.year(), day.month())
+pub fn month_week_for_date(day: NaiveDate, week_start: Weekday) -> Result<MonthWeek> {
+    month_weeks(day.year(), day.month(), week_start)
         .into_iter()
         .find(|w| w.week_start <= day && day <= w.week_end)
         .ok_or_else(|| {
-            anyhow::anyhow!(
-                "Date {} not found in month weeks for {:04}-{:02}",
+            Error::NotFound(format!(
+                "month week containing {} in {:04}-{:02}",
                 day,
                 day.year(),
                 day.month()
-            )
+            ))
         })
 }
+
+/// Which stretch of days a report covers, found from the resolution date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeriodKind {
+    /// The part of the week that falls in the resolution date's month.
+    #[default]
+    MonthWeek,
+    /// The resolution date's calendar month.
+    Month,
+    /// The resolution date's calendar quarter.
+    Quarter,
+    /// The resolution date's calendar year.
+    Year,
+    /// Fixed first and last days, whatever the resolution date.
+    Range { start: NaiveDate, end: NaiveDate },
+    /// The given number of days, ending on the resolution date.
+    RollingDays(u32),
+}
+
+impl PeriodKind {
+    /// Why this kind cannot make a period, if it cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        match self {
+            Self::Range { start, end } if start > end => Some("start is after end"),
+            Self::RollingDays(0) => Some("needs at least one day"),
+            _ => None,
+        }
+    }
+
+    /// The period of this kind for `day`. Month weeks start on `week_start` and are numbered
+    /// as `numbering` says.
+    pub fn period_for_date(
+        &self,
+        day: NaiveDate,
+        week_start: Weekday,
+        numbering: WeekNumbering,
+    ) -> Result<Period> {
+        if let Some(problem) = self.problem() {
+            return Err(Error::ConfigInvalid(format!("period: {problem}")));
+        }
+        let first_of =
+            |month: u32| NaiveDate::from_ymd_opt(day.year(), month, 1).expect("valid month start");
+        let period = match *self {
+            Self::MonthWeek => {
+                let week = month_week_for_date(day, week_start)?;
+                let (number, year) = week.week_of_year(numbering);
+                Period {
+                    start: week.week_start,
+                    end: week.week_end,
+                    name: format!("Week {number} of {year}"),
+                    short_name: format!("Week {number}"),
+                }
+            }
+            Self::Month => Period::named(
+                first_of(day.month()),
+                last_day_of_month(day.year(), day.month()),
+                day.format("%B %Y").to_string(),
+            ),
+            Self::Quarter => {
+                let quarter = (day.month() - 1) / 3 + 1;
+                Period::named(
+                    first_of(quarter * 3 - 2),
+                    last_day_of_month(day.year(), quarter * 3),
+                    format!("Q{quarter} {}", day.year()),
+                )
+            }
+            Self::Year => Period::named(
+                first_of(1),
+                last_day_of_month(day.year(), 12),
+                day.year().to_string(),
+            ),
+            Self::Range { start, end } => Period::between(start, end),
+            Self::RollingDays(days) => {
+                let start = day
+                    .checked_sub_signed(Duration::days(i64::from(days) - 1))
+                    .ok_or_else(|| {
+                        Error::ConfigInvalid(format!(
+                            "period: {days} days before {day} is out of range"
+                        ))
+                    })?;
+                Period::named(start, day, format!("Last {days} days"))
+            }
+        };
+        Ok(period)
+    }
+}
+
+/// The days a report covers, first and last included, with the names its headers show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Name for text headers, such as `Week 11 of 2024` or `Q1 2024`.
+    pub name: String,
+    /// Name for the visual report title, such as `Week 11`.
+    pub short_name: String,
+}
+
+impl Period {
+    /// A custom period from `start` to `end`.
+    pub fn between(start: NaiveDate, end: NaiveDate) -> Self {
+        Self::named(start, end, "Custom period".to_string())
+    }
+
+    fn named(start: NaiveDate, end: NaiveDate, name: String) -> Self {
+        Self {
+            start,
+            end,
+            short_name: name.clone(),
+            name,
+        }
+    }
+
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.start <= day && day <= self.end
+    }
+
+    /// The budget month whose balances go with this period: the month of `day` when the
+    /// period includes it, otherwise the month the period ends in.
+    pub fn budget_month(&self, day: NaiveDate) -> NaiveDate {
+        if self.contains(day) { day } else { self.end }
+    }
+
+    /// The first day of every budget month the period touches, in order. Budgeted amounts
+    /// are summed over all of them.
+    pub fn months(&self) -> Vec<NaiveDate> {
+        let mut months = Vec::new();
+        let mut month = self.start.with_day(1);
+        while let Some(first) = month.filter(|first| *first <= self.end) {
+            months.push(first);
+            month = first.checked_add_months(Months::new(1));
+        }
+        months
+    }
+}
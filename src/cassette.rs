@@ -0,0 +1,388 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::{Error, Result, ResultExt};
+use crate::ynab::{
+    Account, BudgetData, BudgetSummary, Category, CategoryGroup, Delta, MonthDetail,
+    ScheduledTransaction, Transaction, YnabApi,
+};
+
+// --- Cassette layout ---
+
+enum Request<'a> {
+    Budgets,
+    Accounts {
+        budget_id: &'a str,
+    },
+    CategoryGroups {
+        budget_id: &'a str,
+        last_knowledge_of_server: Option<i64>,
+    },
+    MonthCategory {
+        budget_id: &'a str,
+        month: NaiveDate,
+        category_id: &'a str,
+    },
+    Month {
+        budget_id: &'a str,
+        month: NaiveDate,
+    },
+    Transactions {
+        budget_id: &'a str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    },
+    ScheduledTransactions {
+        budget_id: &'a str,
+    },
+}
+
+/// Path of the cassette holding the response to one request, relative to the cassette
+/// directory. Requests with `last_knowledge_of_server` get their own cassette so a recorded
+/// delta sync replays the same sequence of responses.
+fn cassette_name(request: &Request<'_>) -> PathBuf {
+    let knowledge_suffix = |knowledge: Option<i64>| match knowledge {
+        Some(k) => format!("_k{k}"),
+        None => String::new(),
+    };
+    match request {
+        Request::Budgets => PathBuf::from("budgets.json"),
+        Request::Accounts { budget_id } => Path::new(budget_id).join("accounts.json"),
+        Request::CategoryGroups {
+            budget_id,
+            last_knowledge_of_server,
+        } => Path::new(budget_id).join(format!(
+            "category_groups{}.json",
+            knowledge_suffix(*last_knowledge_of_server)
+        )),
+        Request::MonthCategory {
+            budget_id,
+            month,
+            category_id,
+        } => Path::new(budget_id).join("months").join(format!(
+            "{:04}-{:02}_{category_id}.json",
+            month.year(),
+            month.month()
+        )),
+        Request::Month { budget_id, month } => Path::new(budget_id).join("months").join(format!(
+            "{:04}-{:02}.json",
+            month.year(),
+            month.month()
+        )),
+        Request::Transactions {
+            budget_id,
+            since_date,
+            last_knowledge_of_server,
+        } => Path::new(budget_id).join(format!(
+            "transactions_{since_date}{}.json",
+            knowledge_suffix(*last_knowledge_of_server)
+        )),
+        Request::ScheduledTransactions { budget_id } => {
+            Path::new(budget_id).join("scheduled_transactions.json")
+        }
+    }
+}
+
+// --- Recording ---
+
+/// A [`YnabApi`] that forwards every request to `inner` and writes each response as
+/// pretty-printed JSON into a cassette directory, for later use with [`ReplayYnabApi`].
+pub struct RecordingYnabApi<A> {
+    inner: A,
+    dir: PathBuf,
+}
+
+impl<A: YnabApi> RecordingYnabApi<A> {
+    pub fn new(inner: A, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+
+    fn record<T: Serialize>(&self, request: &Request<'_>, response: T) -> Result<T> {
+        let path = self.dir.join(cassette_name(request));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| Error::io(format!("creating cassette directory {parent:?}"), err))?;
+        }
+        let contents = serde_json::to_string_pretty(&response)
+            .shape_context("serializing cassette response")?;
+        std::fs::write(&path, contents)
+            .map_err(|err| Error::io(format!("writing cassette {path:?}"), err))?;
+        Ok(response)
+    }
+}
+
+impl<A: YnabApi> YnabApi for RecordingYnabApi<A> {
+    fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        self.record(&Request::Budgets, self.inner.get_budgets()?)
+    }
+
+    fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
+        let request = Request::CategoryGroups {
+            budget_id,
+            last_knowledge_of_server: None,
+        };
+        self.record(&request, self.inner.get_category_groups(budget_id)?)
+    }
+
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        let request = Request::Accounts { budget_id };
+        self.record(&request, self.inner.get_accounts(budget_id)?)
+    }
+
+    fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        let request = Request::MonthCategory {
+            budget_id,
+            month,
+            category_id,
+        };
+        let category = self
+            .inner
+            .get_month_category(budget_id, month, category_id)?;
+        self.record(&request, category)
+    }
+
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        let request = Request::Month { budget_id, month };
+        self.record(&request, self.inner.get_month_detail(budget_id, month)?)
+    }
+
+    fn get_month_details(&self, budget_id: &str, months: &[NaiveDate]) -> Result<Vec<MonthDetail>> {
+        let details = self.inner.get_month_details(budget_id, months)?;
+        months
+            .iter()
+            .zip(details)
+            .map(|(month, detail)| {
+                let request = Request::Month {
+                    budget_id,
+                    month: *month,
+                };
+                self.record(&request, detail)
+            })
+            .collect()
+    }
+
+    fn get_month_categories_by_id(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_ids: &[&str],
+    ) -> Result<Vec<Category>> {
+        let categories = self
+            .inner
+            .get_month_categories_by_id(budget_id, month, category_ids)?;
+        category_ids
+            .iter()
+            .zip(categories)
+            .map(|(category_id, category)| {
+                let request = Request::MonthCategory {
+                    budget_id,
+                    month,
+                    category_id,
+                };
+                self.record(&request, category)
+            })
+            .collect()
+    }
+
+    fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
+        let request = Request::Transactions {
+            budget_id,
+            since_date,
+            last_knowledge_of_server: None,
+        };
+        self.record(
+            &request,
+            self.inner.get_transactions(budget_id, since_date)?,
+        )
+    }
+
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        let request = Request::ScheduledTransactions { budget_id };
+        self.record(&request, self.inner.get_scheduled_transactions(budget_id)?)
+    }
+
+    /// Fetches through `inner` in one go, so its requests stay concurrent, and records each
+    /// response as the matching single request would.
+    fn get_budget_data(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        since_date: NaiveDate,
+        with_accounts: bool,
+    ) -> Result<BudgetData> {
+        let data = self
+            .inner
+            .get_budget_data(budget_id, month, since_date, with_accounts)?;
+        let groups = Request::CategoryGroups {
+            budget_id,
+            last_knowledge_of_server: None,
+        };
+        let transactions = Request::Transactions {
+            budget_id,
+            since_date,
+            last_knowledge_of_server: None,
+        };
+        Ok(BudgetData {
+            category_groups: self.record(&groups, data.category_groups)?,
+            month: self.record(&Request::Month { budget_id, month }, data.month)?,
+            transactions: self.record(&transactions, data.transactions)?,
+            scheduled_transactions: self.record(
+                &Request::ScheduledTransactions { budget_id },
+                data.scheduled_transactions,
+            )?,
+            accounts: data
+                .accounts
+                .map(|accounts| self.record(&Request::Accounts { budget_id }, accounts))
+                .transpose()?,
+        })
+    }
+
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        let request = Request::CategoryGroups {
+            budget_id,
+            last_knowledge_of_server,
+        };
+        let delta = self
+            .inner
+            .get_category_groups_delta(budget_id, last_knowledge_of_server)?;
+        self.record(&request, delta)
+    }
+
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        let request = Request::Transactions {
+            budget_id,
+            since_date,
+            last_knowledge_of_server,
+        };
+        let delta =
+            self.inner
+                .get_transactions_delta(budget_id, since_date, last_knowledge_of_server)?;
+        self.record(&request, delta)
+    }
+}
+
+// --- Replay ---
+
+/// A [`YnabApi`] that answers every request from a cassette directory written by
+/// [`RecordingYnabApi`], without touching the network. Requests that were never recorded
+/// are an error.
+pub struct ReplayYnabApi {
+    dir: PathBuf,
+}
+
+impl ReplayYnabApi {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn replay<T: DeserializeOwned>(&self, request: &Request<'_>) -> Result<T> {
+        let path = self.dir.join(cassette_name(request));
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::NotFound(format!("recorded cassette {path:?}")));
+            }
+            Err(err) => return Err(Error::io(format!("reading cassette {path:?}"), err)),
+        };
+        serde_json::from_str(&contents).shape_context(format!("parsing cassette {path:?}"))
+    }
+
+    /// Plain and delta requests share cassettes, so a cassette recorded through one can be
+    /// replayed through the other. Plain cassettes replay with a server knowledge of 0.
+    fn replay_delta<T: DeserializeOwned>(&self, request: &Request<'_>) -> Result<Delta<T>> {
+        let value: serde_json::Value = self.replay(request)?;
+        let delta = if value.is_array() {
+            Delta {
+                items: serde_json::from_value(value).shape_context("parsing cassette")?,
+                server_knowledge: 0,
+            }
+        } else {
+            serde_json::from_value(value).shape_context("parsing cassette")?
+        };
+        Ok(delta)
+    }
+}
+
+impl YnabApi for ReplayYnabApi {
+    fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        self.replay(&Request::Budgets)
+    }
+
+    fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
+        Ok(self.get_category_groups_delta(budget_id, None)?.items)
+    }
+
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        self.replay(&Request::Accounts { budget_id })
+    }
+
+    fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        self.replay(&Request::MonthCategory {
+            budget_id,
+            month,
+            category_id,
+        })
+    }
+
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        self.replay(&Request::Month { budget_id, month })
+    }
+
+    fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
+        Ok(self
+            .get_transactions_delta(budget_id, since_date, None)?
+            .items)
+    }
+
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        self.replay(&Request::ScheduledTransactions { budget_id })
+    }
+
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        self.replay_delta(&Request::CategoryGroups {
+            budget_id,
+            last_knowledge_of_server,
+        })
+    }
+
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        self.replay_delta(&Request::Transactions {
+            budget_id,
+            since_date,
+            last_knowledge_of_server,
+        })
+    }
+}
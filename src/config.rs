@@ -1,11 +1,18 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use chrono::NaiveDate;
+use chrono::{NaiveDate, Weekday};
 use indexmap::IndexMap;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::calendar_weeks::{PeriodKind, WeekNumbering};
+use crate::error::{Error, Result};
+use crate::money::CurrencyFormat;
+use crate::secret::{Secret, TokenSources};
+use crate::watch_list::GroupPattern;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum SimpleOutputFormat {
     #[serde(rename = "polars_print")]
     PolarsPrint,
@@ -13,7 +20,7 @@ pub enum SimpleOutputFormat {
     CsvPrint,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum OutputFormat {
     Simple(SimpleOutputFormat),
@@ -27,22 +34,933 @@ impl Default for OutputFormat {
     }
 }
 
+impl OutputFormat {
+    /// The output for one budget of a multi-budget run: files get the budget's name added
+    /// to their stem, e.g. `report.html` becomes `report_joint.html`. Printed output is
+    /// unchanged.
+    pub fn for_budget(&self, budget_name: &str) -> Self {
+        match self {
+            Self::Simple(_) => self.clone(),
+            Self::CsvFile { csv_output } => Self::CsvFile {
+                csv_output: budget_output_path(csv_output, budget_name),
+            },
+            Self::VisualFile { visual_output } => Self::VisualFile {
+                visual_output: budget_output_path(visual_output, budget_name),
+            },
+        }
+    }
+
+    /// Every file this output writes. A CSV report comes with one file per extra section
+    /// next to it, see [`csv_section_path`]; printed output writes none.
+    pub fn files(&self, show_net_worth: bool) -> Vec<PathBuf> {
+        match self {
+            Self::Simple(_) => Vec::new(),
+            Self::CsvFile { csv_output } => {
+                let mut sections = vec!["category_group_totals", "upcoming", "budget_month"];
+                if show_net_worth {
+                    sections.push("net_worth");
+                }
+                std::iter::once(csv_output.clone())
+                    .chain(
+                        sections
+                            .into_iter()
+                            .map(|section| csv_section_path(csv_output, section)),
+                    )
+                    .collect()
+            }
+            Self::VisualFile { visual_output } => vec![visual_output.clone()],
+        }
+    }
+}
+
+/// The file one extra report section is written to next to `csv_output`, e.g.
+/// `report_upcoming.csv` for the `upcoming` section of `report.csv`.
+pub fn csv_section_path(csv_output: &Path, section: &str) -> PathBuf {
+    let stem = csv_output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("report");
+    let ext = csv_output
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("csv");
+    csv_output.with_file_name(format!("{stem}_{section}.{ext}"))
+}
+
+fn budget_output_path(path: &Path, budget_name: &str) -> PathBuf {
+    let mut slug = String::new();
+    for c in budget_name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    let slug = match slug.trim_end_matches('_') {
+        "" => "budget",
+        slug => slug,
+    };
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("report");
+    let file_name = match path.extension().and_then(|s| s.to_str()) {
+        Some(ext) => format!("{stem}_{slug}.{ext}"),
+        None => format!("{stem}_{slug}"),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Settings that replace parts of the currency format YNAB reports for the budget. Unset
+/// fields keep the budget's own setting.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyFormatOverride {
+    #[serde(default)]
+    pub currency_symbol: Option<String>,
+    #[serde(default)]
+    pub symbol_first: Option<bool>,
+    #[serde(default)]
+    pub display_symbol: Option<bool>,
+    #[serde(default)]
+    pub decimal_digits: Option<u32>,
+    #[serde(default)]
+    pub decimal_separator: Option<String>,
+    #[serde(default)]
+    pub group_separator: Option<String>,
+}
+
+impl CurrencyFormatOverride {
+    pub fn apply(&self, base: CurrencyFormat) -> CurrencyFormat {
+        CurrencyFormat {
+            currency_symbol: self.currency_symbol.clone().unwrap_or(base.currency_symbol),
+            symbol_first: self.symbol_first.unwrap_or(base.symbol_first),
+            display_symbol: self.display_symbol.unwrap_or(base.display_symbol),
+            decimal_digits: self.decimal_digits.unwrap_or(base.decimal_digits),
+            decimal_separator: self
+                .decimal_separator
+                .clone()
+                .unwrap_or(base.decimal_separator),
+            group_separator: self.group_separator.clone().unwrap_or(base.group_separator),
+            ..base
+        }
+    }
+}
+
+/// Which categories of one group the report shows, what they are called and in what order.
+/// Applied to every output alike.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryRules {
+    /// Categories of a watched group to leave out.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Categories to show from a group that is not on the watch list.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Names to show instead of the category names in YNAB.
+    #[serde(default)]
+    pub aliases: IndexMap<String, String>,
+    /// Categories to list first, in this order. The rest follow alphabetically.
+    #[serde(default)]
+    pub order: Vec<String>,
+}
+
+/// One budget to report on, with the category groups to show and their colours.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Config {
+pub struct BudgetConfig {
     pub budget_name: String,
-    pub personal_access_token: String,
     pub category_group_watch_list: IndexMap<String, String>,
+    /// Per-group category rules, keyed like the watch list, see [`GroupPattern`].
+    #[serde(default)]
+    pub category_rules: IndexMap<String, CategoryRules>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// The budget to report on. Leave unset and list `budgets` instead to report on several.
+    #[serde(default)]
+    pub budget_name: Option<String>,
+    /// The token written into the config. Prefer `personal_access_token_from`.
+    #[serde(default)]
+    pub personal_access_token: Option<Secret>,
+    /// Where to read the token from instead, see [`TokenSources`] for the precedence.
+    #[serde(default)]
+    pub personal_access_token_from: TokenSources,
+    #[serde(default)]
+    pub category_group_watch_list: IndexMap<String, String>,
+    /// Per-group category rules for `budgetName`, keyed like the watch list, see
+    /// [`GroupPattern`].
+    #[serde(default)]
+    pub category_rules: IndexMap<String, CategoryRules>,
+    /// Budgets to report on in one run, one section each.
+    #[serde(default)]
+    pub budgets: Vec<BudgetConfig>,
     #[serde(default)]
     pub resolution_date: Option<NaiveDate>,
+    /// The stretch of days around the resolution date to report on.
+    #[serde(default)]
+    pub period: PeriodKind,
+    /// The day weeks start on, such as `"Mon"` or `"Sunday"`. Ignored for ISO weeks, which
+    /// always start on Monday; use [`Config::first_weekday`].
+    #[serde(default = "default_week_start")]
+    pub week_start: Weekday,
+    /// How the report headers number weeks.
+    #[serde(default)]
+    pub week_numbering: WeekNumbering,
     #[serde(default)]
     pub show_all_rows: bool,
+    /// Where the report goes: one output or a list of them, all rendered from the same
+    /// fetched data.
+    #[serde(
+        rename = "outputFormat",
+        default = "default_output_formats",
+        deserialize_with = "one_or_many_outputs"
+    )]
+    pub output_formats: Vec<OutputFormat>,
+    /// Adds a net worth summary of every account to the report.
     #[serde(default)]
-    pub output_format: OutputFormat,
+    pub show_net_worth: bool,
+    #[serde(default)]
+    pub currency_format: CurrencyFormatOverride,
+    /// Where the config was loaded from, for pointing at problems.
+    #[serde(skip)]
+    pub source: ConfigSource,
+}
+
+fn default_week_start() -> Weekday {
+    Weekday::Sun
+}
+
+fn default_output_formats() -> Vec<OutputFormat> {
+    vec![OutputFormat::default()]
+}
+
+fn one_or_many_outputs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<OutputFormat>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(OutputFormat),
+        Many(Vec<OutputFormat>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(output) => vec![output],
+        OneOrMany::Many(outputs) => outputs,
+    })
+}
+
+/// Which file each config key was read from, the keys that were not recognised and the
+/// deprecated keys that were read under their new names.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource {
+    origins: HashMap<String, KeyOrigin>,
+    unknown_keys: Vec<String>,
+    deprecated_keys: Vec<ConfigProblem>,
+}
+
+/// The file a key was read from and the key as written there, which differs from the
+/// key in the config for settings that came from a profile.
+#[derive(Debug, Clone)]
+struct KeyOrigin {
+    file: PathBuf,
+    key: String,
+}
+
+impl ConfigSource {
+    fn record(&mut self, file: &Path, key: String, value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (name, value) in map {
+                    self.record(file, join_key(&key, name), value);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for (index, value) in items.iter().enumerate() {
+                    self.record(file, format!("{key}[{index}]"), value);
+                }
+            }
+            _ => {}
+        }
+        if !key.is_empty() {
+            let origin = KeyOrigin {
+                file: file.to_path_buf(),
+                key: key.clone(),
+            };
+            self.origins.insert(key, origin);
+        }
+    }
+
+    /// Makes the keys under `from` also the origins of the same keys at the top level,
+    /// for a profile merged over the shared settings.
+    fn lift(&mut self, from: &str) {
+        let lifted: Vec<(String, KeyOrigin)> = self
+            .origins
+            .iter()
+            .filter_map(|(key, origin)| {
+                let key = key.strip_prefix(from)?.strip_prefix('.')?;
+                Some((key.to_string(), origin.clone()))
+            })
+            .collect();
+        self.origins.extend(lifted);
+    }
+
+    /// Where `key`, or else the nearest enclosing key, was read from: the file and the key
+    /// as written in it.
+    fn locate(&self, key: &str) -> Option<(&Path, String)> {
+        let mut prefix = key;
+        loop {
+            if let Some(origin) = self.origins.get(prefix) {
+                let written = format!("{}{}", origin.key, &key[prefix.len()..]);
+                return Some((&origin.file, written));
+            }
+            prefix = &prefix[..prefix.rfind(['.', '['])?];
+        }
+    }
+
+    /// The file that set `key`, or else the nearest enclosing key that has one.
+    pub fn file_for(&self, key: &str) -> Option<&Path> {
+        self.locate(key).map(|(file, _)| file)
+    }
+
+    fn problem(&self, key: impl Into<String>, message: impl Into<String>) -> ConfigProblem {
+        let key = key.into();
+        let (file, key) = match self.locate(&key) {
+            Some((file, written)) => (Some(file.to_path_buf()), written),
+            None => (None, key),
+        };
+        ConfigProblem {
+            file,
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+fn join_key(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{parent}.{name}")
+    }
+}
+
+/// One thing wrong with a config, and where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The file the offending key was read from, if the config came from files.
+    pub file: Option<PathBuf>,
+    /// Path to the key, e.g. `budgets[1].categoryGroupWatchList.Bills`.
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}: {}: {}", file.display(), self.key, self.message),
+            None => write!(f, "{}: {}", self.key, self.message),
+        }
+    }
+}
+
+impl Config {
+    /// Checks everything the types alone do not: watch list keys and colours, category rules,
+    /// the period, output paths, which budgets are configured and unrecognised keys. Reports
+    /// every problem at once.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ConfigProblems(problems))
+        }
+    }
+
+    /// Things worth fixing in the config that do not stop it being used, such as deprecated
+    /// keys.
+    pub fn warnings(&self) -> &[ConfigProblem] {
+        &self.source.deprecated_keys
+    }
+
+    /// Everything [`Config::validate`] would complain about.
+    pub fn problems(&self) -> Vec<ConfigProblem> {
+        let source = &self.source;
+        let mut problems: Vec<ConfigProblem> = source
+            .unknown_keys
+            .iter()
+            .map(|key| source.problem(key.as_str(), "unknown key"))
+            .collect();
+
+        if let Err(Error::ConfigInvalid(message)) = self.budget_configs() {
+            let key = if self.budgets.is_empty() {
+                "budgetName"
+            } else {
+                "budgets"
+            };
+            problems.push(source.problem(key, message));
+        }
+
+        let watch_lists = std::iter::once((
+            "categoryGroupWatchList".to_string(),
+            &self.category_group_watch_list,
+        ))
+        .chain(self.budgets.iter().enumerate().map(|(index, budget)| {
+            (
+                format!("budgets[{index}].categoryGroupWatchList"),
+                &budget.category_group_watch_list,
+            )
+        }));
+        for (parent, watch_list) in watch_lists {
+            for (group, color) in watch_list {
+                if let Err(Error::ConfigInvalid(message)) = GroupPattern::parse(group) {
+                    problems.push(source.problem(join_key(&parent, group), message));
+                }
+                if !is_hex_color(color) {
+                    problems.push(source.problem(
+                        join_key(&parent, group),
+                        format!("{color:?} is not a colour like \"#dfe7f5\""),
+                    ));
+                }
+            }
+        }
+
+        let rule_sets = std::iter::once(("categoryRules".to_string(), &self.category_rules)).chain(
+            self.budgets.iter().enumerate().map(|(index, budget)| {
+                (
+                    format!("budgets[{index}].categoryRules"),
+                    &budget.category_rules,
+                )
+            }),
+        );
+        for (parent, rule_set) in rule_sets {
+            for (group, rules) in rule_set {
+                if let Err(Error::ConfigInvalid(message)) = GroupPattern::parse(group) {
+                    problems.push(source.problem(join_key(&parent, group), message));
+                }
+                for category in rules.exclude.iter().filter(|c| rules.include.contains(c)) {
+                    problems.push(source.problem(
+                        join_key(&join_key(&parent, group), "exclude"),
+                        format!("{category:?} is also in include"),
+                    ));
+                }
+            }
+        }
+
+        if let Some(message) = self.period.problem() {
+            problems.push(source.problem("period", message));
+        }
+
+        if self.output_formats.is_empty() {
+            problems.push(source.problem("outputFormat", "needs at least one output"));
+        }
+        let mut written: Vec<PathBuf> = Vec::new();
+        for (index, output) in self.output_formats.iter().enumerate() {
+            let (name, path) = match output {
+                OutputFormat::Simple(_) => continue,
+                OutputFormat::CsvFile { csv_output } => ("csv_output", csv_output),
+                OutputFormat::VisualFile { visual_output } => ("visual_output", visual_output),
+            };
+            let parent = if self.output_formats.len() > 1 {
+                format!("outputFormat[{index}]")
+            } else {
+                "outputFormat".to_string()
+            };
+            let key = join_key(&parent, name);
+            if let Some(message) = output_path_problem(path) {
+                problems.push(source.problem(key.as_str(), message));
+            }
+            let files = output.files(self.show_net_worth);
+            if let Some(file) = files.iter().find(|file| written.contains(file)) {
+                problems
+                    .push(source.problem(key, format!("another output already writes {file:?}")));
+            }
+            written.extend(files);
+        }
+
+        if self.budgets.len() > 1 {
+            let mut budget_files: Vec<(&str, PathBuf)> = Vec::new();
+            for (index, budget) in self.budgets.iter().enumerate() {
+                let files: Vec<PathBuf> = self
+                    .output_formats
+                    .iter()
+                    .flat_map(|output| {
+                        output
+                            .for_budget(&budget.budget_name)
+                            .files(self.show_net_worth)
+                    })
+                    .collect();
+                let clash = files.iter().find_map(|file| {
+                    budget_files
+                        .iter()
+                        .find(|(_, written)| written == file)
+                        .map(|(other, _)| (*other, file))
+                });
+                if let Some((other, file)) = clash {
+                    problems.push(source.problem(
+                        format!("budgets[{index}].budgetName"),
+                        format!("writes {file:?} like budget {other:?}, rename one of them"),
+                    ));
+                }
+                budget_files.extend(
+                    files
+                        .into_iter()
+                        .map(|file| (budget.budget_name.as_str(), file)),
+                );
+            }
+        }
+
+        problems
+    }
+
+    /// The day report weeks start on, taking `week_numbering` into account.
+    pub fn first_weekday(&self) -> Weekday {
+        self.week_numbering.week_start(self.week_start)
+    }
+
+    /// The YNAB personal access token, read from the first configured source.
+    pub fn personal_access_token(&self) -> Result<Secret> {
+        self.personal_access_token_from
+            .resolve(self.personal_access_token.as_ref())
+    }
+
+    /// Every budget this config reports on: the entries of `budgets`, or the single
+    /// `budgetName` with the top-level watch list.
+    pub fn budget_configs(&self) -> Result<Vec<BudgetConfig>> {
+        match (&self.budget_name, self.budgets.is_empty()) {
+            (Some(_), false) => Err(Error::ConfigInvalid(
+                "set either budgetName or budgets, not both".to_string(),
+            )),
+            (Some(budget_name), true) => Ok(vec![BudgetConfig {
+                budget_name: budget_name.clone(),
+                category_group_watch_list: self.category_group_watch_list.clone(),
+                category_rules: self.category_rules.clone(),
+            }]),
+            (None, false) => Ok(self.budgets.clone()),
+            (None, true) => Err(Error::ConfigInvalid(
+                "no budget configured: set budgetName or budgets".to_string(),
+            )),
+        }
+    }
+}
+
+/// Values given on the command line, applied over the config files.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Entry of the config's `profiles` to merge over its shared settings, before the
+    /// other overrides.
+    pub profile: Option<String>,
+    pub resolution_date: Option<NaiveDate>,
+    pub period: Option<PeriodKind>,
+    /// Budgets to report on. Picks entries of `budgets` by name when the config lists
+    /// several, and otherwise replaces `budgetName`.
+    pub budget_names: Vec<String>,
+    pub output_format: Option<OutputFormat>,
+    pub show_all_rows: Option<bool>,
+    /// Replaces the watch list of every budget.
+    pub category_group_watch_list: IndexMap<String, String>,
+}
+
+/// Stands in for a file name when a config problem comes from a command-line override.
+const COMMAND_LINE: &str = "command line";
+
+/// Settings a profile replaces as a whole rather than merging into the shared ones.
+const PROFILE_REPLACED_KEYS: [&str; 2] = ["categoryGroupWatchList", "categoryRules"];
+
+impl ConfigOverrides {
+    fn apply(
+        &self,
+        merged: &mut serde_json::Value,
+        source: &mut ConfigSource,
+    ) -> Vec<ConfigProblem> {
+        let mut problems = self.apply_profile(merged, source);
+        let Some(map) = merged.as_object_mut() else {
+            return problems;
+        };
+
+        if let Some(date) = self.resolution_date {
+            set_override(map, source, "resolutionDate", date.to_string().into());
+        }
+        if let Some(period) = &self.period {
+            let value = serde_json::to_value(period).expect("periods serialize to JSON");
+            set_override(map, source, "period", value);
+        }
+        if let Some(show_all_rows) = self.show_all_rows {
+            set_override(map, source, "showAllRows", show_all_rows.into());
+        }
+        if let Some(output_format) = &self.output_format {
+            let value =
+                serde_json::to_value(output_format).expect("output formats serialize to JSON");
+            set_override(map, source, "outputFormat", value);
+        }
+
+        let names = self.budget_names.as_slice();
+        if let Some(serde_json::Value::Array(budgets)) = map.get_mut("budgets")
+            && !budgets.is_empty()
+            && !names.is_empty()
+        {
+            let budget_name = |budget: &serde_json::Value| {
+                budget
+                    .get("budgetName")
+                    .and_then(serde_json::Value::as_str)
+                    .map(str::to_string)
+            };
+            for name in names {
+                if !budgets
+                    .iter()
+                    .any(|b| budget_name(b).as_ref() == Some(name))
+                {
+                    problems.push(source.problem(
+                        "budgets",
+                        format!("no budget named {name:?} to pick with --budget"),
+                    ));
+                }
+            }
+            budgets.retain(|b| budget_name(b).is_some_and(|n| names.contains(&n)));
+        } else if let [name] = names {
+            set_override(map, source, "budgetName", name.as_str().into());
+        } else if !names.is_empty() {
+            let watch_list = map
+                .remove("categoryGroupWatchList")
+                .unwrap_or_else(|| serde_json::json!({}));
+            let category_rules = map
+                .remove("categoryRules")
+                .unwrap_or_else(|| serde_json::json!({}));
+            map.remove("budgetName");
+            let budgets = names
+                .iter()
+                .map(|name| {
+                    serde_json::json!({
+                        "budgetName": name,
+                        "categoryGroupWatchList": watch_list,
+                        "categoryRules": category_rules,
+                    })
+                })
+                .collect();
+            set_override(map, source, "budgets", budgets);
+        }
+
+        if !self.category_group_watch_list.is_empty() {
+            let watch_list = serde_json::json!(self.category_group_watch_list);
+            match map.get_mut("budgets") {
+                Some(serde_json::Value::Array(budgets)) if !budgets.is_empty() => {
+                    for (index, budget) in budgets.iter_mut().enumerate() {
+                        if let Some(budget) = budget.as_object_mut() {
+                            let key = format!("budgets[{index}].categoryGroupWatchList");
+                            source.record(Path::new(COMMAND_LINE), key, &watch_list);
+                            budget.insert("categoryGroupWatchList".to_string(), watch_list.clone());
+                        }
+                    }
+                }
+                _ => set_override(map, source, "categoryGroupWatchList", watch_list),
+            }
+        }
+
+        problems
+    }
+
+    /// Takes `profiles` out of the config and deep merges the chosen one, if any, over
+    /// the rest. A watch list or category rules in the profile replace the shared ones
+    /// instead, since a profile usually narrows down what to report on.
+    fn apply_profile(
+        &self,
+        merged: &mut serde_json::Value,
+        source: &mut ConfigSource,
+    ) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let profiles = merged
+            .as_object_mut()
+            .and_then(|map| map.remove("profiles"));
+        let Some(name) = &self.profile else {
+            return problems;
+        };
+        let mut profiles = match profiles {
+            Some(serde_json::Value::Object(profiles)) => profiles,
+            Some(_) => {
+                problems.push(source.problem("profiles", "must be a table of named profiles"));
+                return problems;
+            }
+            None => {
+                problems
+                    .push(source.problem("profiles", format!("no profiles to pick {name:?} from")));
+                return problems;
+            }
+        };
+        match profiles.remove(name) {
+            Some(mut profile) => {
+                let key = format!("profiles.{name}");
+                let nested = profile.as_object_mut().and_then(|p| p.remove("profiles"));
+                if nested.is_some() {
+                    problems.push(source.problem(
+                        join_key(&key, "profiles"),
+                        "profiles cannot contain profiles",
+                    ));
+                }
+                if let (Some(shared), Some(profile)) = (merged.as_object_mut(), profile.as_object())
+                {
+                    for replaced in PROFILE_REPLACED_KEYS {
+                        if profile.contains_key(replaced) {
+                            shared.remove(replaced);
+                        }
+                    }
+                }
+                source.lift(&key);
+                deep_merge(merged, profile);
+            }
+            None => {
+                let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                problems.push(source.problem(
+                    "profiles",
+                    format!("no profile named {name:?}; there are {}", names.join(", ")),
+                ));
+            }
+        }
+        problems
+    }
+}
+
+fn set_override(
+    map: &mut serde_json::Map<String, serde_json::Value>,
+    source: &mut ConfigSource,
+    key: &str,
+    value: serde_json::Value,
+) {
+    source.record(Path::new(COMMAND_LINE), key.to_string(), &value);
+    map.insert(key.to_string(), value);
 }
 
 pub fn load_config(path: &Path) -> Result<Config> {
-    let contents =
-        std::fs::read_to_string(path).with_context(|| format!("reading config from {path:?}"))?;
-    serde_json::from_str(&contents).with_context(|| "parsing config JSON")
+    load_layered_config(&[path])
+}
+
+/// Loads a config split over several files, e.g. a base config, secrets and local overrides.
+/// Each file is read as JSON, TOML or YAML according to its extension, and later files are
+/// deep merged over earlier ones: tables are merged key by key, anything else (including
+/// lists) is replaced.
+///
+/// The result is validated with [`Config::validate`]; problems that stop the config being
+/// read at all, such as a malformed date, are reported together with those.
+pub fn load_layered_config(paths: &[impl AsRef<Path>]) -> Result<Config> {
+    load_config_with_overrides(paths, &ConfigOverrides::default())
+}
+
+/// Like [`load_layered_config`], with command-line `overrides` applied over the files.
+pub fn load_config_with_overrides(
+    paths: &[impl AsRef<Path>],
+    overrides: &ConfigOverrides,
+) -> Result<Config> {
+    let mut merged = serde_json::Value::Object(serde_json::Map::new());
+    let mut source = ConfigSource::default();
+    for path in paths {
+        let layer = read_config_layer(path.as_ref())?;
+        source.record(path.as_ref(), String::new(), &layer);
+        deep_merge(&mut merged, layer);
+    }
+
+    let mut problems = overrides.apply(&mut merged, &mut source);
+    rename_deprecated_keys(&mut merged, &mut source);
+    problems.extend(take_unreadable_values(&mut merged, &source));
+    let mut unknown_keys = Vec::new();
+    let mut record_unknown = |path: serde_ignored::Path| unknown_keys.push(ignored_key(&path));
+    let parsed: std::result::Result<Config, _> = serde_path_to_error::deserialize(
+        serde_ignored::Deserializer::new(merged, &mut record_unknown),
+    );
+    match parsed {
+        Ok(mut cfg) => {
+            source.unknown_keys = unknown_keys;
+            cfg.source = source;
+            problems.extend(cfg.problems());
+            if problems.is_empty() {
+                Ok(cfg)
+            } else {
+                Err(Error::ConfigProblems(problems))
+            }
+        }
+        Err(err) => {
+            problems.push(source.problem(err.path().to_string(), err.inner().to_string()));
+            Err(Error::ConfigProblems(problems))
+        }
+    }
+}
+
+/// Top-level keys that were renamed, by old name.
+const DEPRECATED_KEYS: [(&str, &str); 1] = [("resolution_date", "resolutionDate")];
+
+/// Moves deprecated keys to their new names, noting each one in `source`. A key also set
+/// under its new name, e.g. by a command-line override, is dropped in favour of that.
+fn rename_deprecated_keys(merged: &mut serde_json::Value, source: &mut ConfigSource) {
+    let Some(map) = merged.as_object_mut() else {
+        return;
+    };
+    for (old, new) in DEPRECATED_KEYS {
+        let Some(value) = map.remove(old) else {
+            continue;
+        };
+        source
+            .deprecated_keys
+            .push(source.problem(old, format!("deprecated, use {new} instead")));
+        if !map.contains_key(new) {
+            if let Some(origin) = source.origins.get(old).cloned() {
+                source.origins.insert(new.to_string(), origin);
+            }
+            map.insert(new.to_string(), value);
+        }
+    }
+}
+
+/// Checks the values serde would reject or silently misread, and removes them so the rest
+/// of the config can still be read and checked.
+fn take_unreadable_values(
+    merged: &mut serde_json::Value,
+    source: &ConfigSource,
+) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    let Some(map) = merged.as_object_mut() else {
+        return problems;
+    };
+
+    if let Some(date) = map.get("resolutionDate")
+        && !date.is_null()
+        && !date.as_str().is_some_and(is_iso_date)
+    {
+        problems.push(source.problem(
+            "resolutionDate",
+            format!("{date} is not a date like \"2024-03-13\""),
+        ));
+        map.remove("resolutionDate");
+    }
+
+    let outputs: Vec<(String, &serde_json::Map<String, serde_json::Value>)> =
+        match map.get("outputFormat") {
+            Some(serde_json::Value::Object(output)) => vec![("outputFormat".to_string(), output)],
+            Some(serde_json::Value::Array(outputs)) => outputs
+                .iter()
+                .enumerate()
+                .filter_map(|(index, output)| {
+                    Some((format!("outputFormat[{index}]"), output.as_object()?))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+    let mut unreadable_output = false;
+    for (key, output) in outputs {
+        let targets: Vec<&String> = output
+            .keys()
+            .filter(|name| matches!(name.as_str(), "csv_output" | "visual_output"))
+            .collect();
+        for name in output.keys().filter(|name| !targets.contains(name)) {
+            problems.push(source.problem(join_key(&key, name), "unknown key"));
+        }
+        match targets.len() {
+            0 => problems.push(source.problem(key, "needs one of csv_output or visual_output")),
+            1 => {}
+            _ => problems
+                .push(source.problem(key, "csv_output and visual_output cannot both be set")),
+        }
+        unreadable_output |= targets.len() != 1 || output.len() != 1;
+    }
+    if unreadable_output {
+        map.remove("outputFormat");
+    }
+
+    problems
+}
+
+/// A key serde skipped, written the same way as `serde_path_to_error` paths.
+fn ignored_key(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => format!("{}[{index}]", ignored_key(parent)),
+        serde_ignored::Path::Map { parent, key } => join_key(&ignored_key(parent), key),
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_key(parent),
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_iso_date(date: &str) -> bool {
+    date.len() == 10 && NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+}
+
+fn output_path_problem(path: &Path) -> Option<String> {
+    if path.as_os_str().is_empty() {
+        return Some("is empty".to_string());
+    }
+    if path.is_dir() {
+        return Some(format!("{} is a directory", path.display()));
+    }
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            Some(format!("directory {} does not exist", parent.display()))
+        }
+        _ => None,
+    }
+}
+
+/// Reads one config file into a JSON value, parsing it by extension.
+pub fn read_config_layer(path: &Path) -> Result<serde_json::Value> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| Error::io(format!("reading config from {path:?}"), err))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") | None => serde_json::from_str(&contents)
+            .map_err(|err| Error::ConfigInvalid(format!("parsing config JSON {path:?}: {err}"))),
+        Some("toml") => contents
+            .parse::<toml::Table>()
+            .map(|table| toml_to_json(toml::Value::Table(table)))
+            .map_err(|err| Error::ConfigInvalid(format!("parsing config TOML {path:?}: {err}"))),
+        Some("yaml" | "yml") => serde_norway::from_str(&contents)
+            .map_err(|err| Error::ConfigInvalid(format!("parsing config YAML {path:?}: {err}"))),
+        Some(other) => Err(Error::ConfigInvalid(format!(
+            "unsupported config format {other:?} for {path:?}: use .json, .toml, .yaml or .yml"
+        ))),
+    }
+}
+
+/// TOML has a date type of its own; dates become the same strings they would be in JSON.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => s.into(),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(dt) => dt.to_string().into(),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(key, value)| (key, toml_to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+/// Merges `overlay` into `base`: objects are merged key by key, recursively, and any other
+/// value in `overlay` replaces the one in `base`.
+pub fn deep_merge(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
@@ -0,0 +1,77 @@
+use std::fmt::Display;
+
+use crate::config::ConfigProblem;
+use crate::ynab::RateLimitExceeded;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong inside crustynab, split by what a caller would do about it.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// YNAB rejected the personal access token.
+    #[error("YNAB rejected the access token: {0}")]
+    Auth(String),
+
+    /// A budget, category, month or recorded response that was asked for does not exist.
+    #[error("{0} not found")]
+    NotFound(String),
+
+    /// YNAB's hourly request quota is used up.
+    #[error(transparent)]
+    RateLimited(#[from] RateLimitExceeded),
+
+    /// A YNAB request failed for a reason retrying did not fix, e.g. a server or network
+    /// error.
+    #[error("{what} failed: {detail}")]
+    Api { what: String, detail: String },
+
+    /// The config could not be parsed or holds values that cannot be used.
+    #[error("invalid config: {0}")]
+    ConfigInvalid(String),
+
+    /// The config was read but holds values that cannot be used, listed with the file and
+    /// key of each.
+    #[error("invalid config:{}", .0.iter().map(|problem| format!("\n  {problem}")).collect::<String>())]
+    ConfigProblems(Vec<ConfigProblem>),
+
+    /// The personal access token could not be read from where the config says it is.
+    /// Never includes the token itself.
+    #[error("reading the personal access token: {0}")]
+    Token(String),
+
+    /// Data from YNAB, a cache or a cassette, or a table built from it, did not have the
+    /// expected shape.
+    #[error("unexpected data: {0}")]
+    DataShape(String),
+
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A report could not be rendered in the requested output format.
+    #[error("rendering output: {0}")]
+    Output(String),
+}
+
+impl Error {
+    pub(crate) fn io(context: impl Display, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.to_string(),
+            source,
+        }
+    }
+}
+
+pub(crate) trait ResultExt<T> {
+    /// Turns a polars or serde failure into [`Error::DataShape`], prefixed with `context`.
+    fn shape_context(self, context: impl Display) -> Result<T>;
+}
+
+impl<T, E: Display> ResultExt<T> for std::result::Result<T, E> {
+    fn shape_context(self, context: impl Display) -> Result<T> {
+        self.map_err(|err| Error::DataShape(format!("{context}: {err}")))
+    }
+}
@@ -1,5 +1,13 @@
+pub mod cache;
 pub mod calendar_weeks;
+pub mod cassette;
 pub mod config;
+mod error;
+pub mod money;
 pub mod report;
+pub mod secret;
 pub mod visual_report;
+pub mod watch_list;
 pub mod ynab;
+
+pub use error::{Error, Result};
@@ -2,179 +2,544 @@ use std::collections::HashSet;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use chrono::Datelike;
-use clap::Parser;
+use chrono::{Datelike, NaiveDate};
+use clap::{Parser, ValueEnum};
 use polars::prelude::*;
 
-use crustynab::calendar_weeks::month_week_for_date;
-use crustynab::config::{self, OutputFormat, SimpleOutputFormat};
+use crustynab::cache::CachingYnabApi;
+use crustynab::calendar_weeks::PeriodKind;
+use crustynab::cassette::{RecordingYnabApi, ReplayYnabApi};
+use crustynab::config::{
+    self, ConfigOverrides, OutputFormat, SimpleOutputFormat, csv_section_path,
+};
 use crustynab::report;
-use crustynab::visual_report::build_visual_report_html;
-use crustynab::ynab::{HttpYnabClient, YnabApi};
+use crustynab::visual_report::{PlannedYear, ReportSections, build_visual_report_html};
+use crustynab::watch_list;
+use crustynab::ynab::{
+    BudgetSummary, DEFAULT_MAX_CONCURRENT_REQUESTS, HttpYnabClient, RetryPolicy, YnabApi,
+    get_month_categories_for,
+};
 
 #[derive(Parser, Debug)]
 #[clap(author = "Simon Zeng", version, about = "YNAB budget reporting tool")]
 struct Args {
-    /// Path to config.json
+    /// Config file (.json, .toml, .yaml or .yml). Repeat to layer files, later ones
+    /// overriding earlier ones
     #[arg(short, long, default_value = "config.json")]
-    config: PathBuf,
+    config: Vec<PathBuf>,
+
+    /// Keep a local copy of YNAB data in this directory and only fetch changes on later runs
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Maximum number of YNAB requests in flight at once
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS)]
+    max_concurrent_requests: usize,
+
+    /// How often to retry a YNAB request that was rate limited or hit a transient server error
+    #[arg(long, default_value_t = RetryPolicy::default().max_retries)]
+    max_retries: u32,
+
+    /// Record every YNAB response as JSON cassettes in this directory
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer every YNAB request from cassettes in this directory instead of the network
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Apply this entry of the config's `profiles` over its shared settings
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Report on the period containing this date (YYYY-MM-DD) instead of resolutionDate
+    #[arg(long)]
+    date: Option<NaiveDate>,
+
+    /// Period to report on around the date, replacing the config's period
+    #[arg(long, value_enum, conflicts_with_all = ["from", "last_days"])]
+    period: Option<PeriodArg>,
+
+    /// First day (YYYY-MM-DD) of a custom period to report on, up to --to
+    #[arg(long, requires = "to", conflicts_with = "last_days")]
+    from: Option<NaiveDate>,
+
+    /// Last day (YYYY-MM-DD) of a custom period to report on, from --from
+    #[arg(long, requires = "from")]
+    to: Option<NaiveDate>,
+
+    /// Report on this many days, ending on the date
+    #[arg(long, value_name = "DAYS")]
+    last_days: Option<u32>,
+
+    /// Report on this budget. Repeat for several; picks from `budgets` when the config
+    /// lists them
+    #[arg(long = "budget", value_name = "NAME")]
+    budgets: Vec<String>,
+
+    /// Output format, replacing outputFormat. csv and visual write to --output
+    #[arg(
+        long,
+        value_enum,
+        requires_if("csv", "output"),
+        requires_if("visual", "output")
+    )]
+    format: Option<FormatArg>,
+
+    /// File to write the report to. Without --format, .html means visual and anything else
+    /// csv
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Show every watched category, not only those with activity in the period
+    #[arg(long, overrides_with = "no_show_all_rows")]
+    show_all_rows: bool,
+
+    /// Show only categories with activity in the period, even if the config sets showAllRows
+    #[arg(long, overrides_with = "show_all_rows")]
+    no_show_all_rows: bool,
+
+    /// Category group to report on and its colour, replacing the watch list. Repeatable
+    #[arg(long = "group", value_name = "NAME=#COLOR", value_parser = parse_group)]
+    groups: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PeriodArg {
+    MonthWeek,
+    Month,
+    Quarter,
+    Year,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FormatArg {
+    PolarsPrint,
+    CsvPrint,
+    Csv,
+    Visual,
+}
+
+fn parse_group(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.rsplit_once('=')
+        .map(|(name, color)| (name.to_string(), color.to_string()))
+        .ok_or_else(|| format!("expected NAME=#COLOR, got {arg:?}"))
+}
+
+impl Args {
+    fn config_overrides(&self) -> ConfigOverrides {
+        let output_format = match (self.format, &self.output) {
+            (Some(FormatArg::PolarsPrint), _) => {
+                Some(OutputFormat::Simple(SimpleOutputFormat::PolarsPrint))
+            }
+            (Some(FormatArg::CsvPrint), _) => {
+                Some(OutputFormat::Simple(SimpleOutputFormat::CsvPrint))
+            }
+            (Some(FormatArg::Visual), Some(path)) => Some(OutputFormat::VisualFile {
+                visual_output: path.clone(),
+            }),
+            (None, Some(path)) if path.extension().is_some_and(|ext| ext == "html") => {
+                Some(OutputFormat::VisualFile {
+                    visual_output: path.clone(),
+                })
+            }
+            (Some(FormatArg::Csv) | None, Some(path)) => Some(OutputFormat::CsvFile {
+                csv_output: path.clone(),
+            }),
+            (Some(FormatArg::Csv | FormatArg::Visual), None) | (None, None) => None,
+        };
+        let period = match (self.period, self.from.zip(self.to), self.last_days) {
+            (Some(PeriodArg::MonthWeek), _, _) => Some(PeriodKind::MonthWeek),
+            (Some(PeriodArg::Month), _, _) => Some(PeriodKind::Month),
+            (Some(PeriodArg::Quarter), _, _) => Some(PeriodKind::Quarter),
+            (Some(PeriodArg::Year), _, _) => Some(PeriodKind::Year),
+            (None, Some((start, end)), _) => Some(PeriodKind::Range { start, end }),
+            (None, None, Some(days)) => Some(PeriodKind::RollingDays(days)),
+            (None, None, None) => None,
+        };
+        ConfigOverrides {
+            profile: self.profile.clone(),
+            resolution_date: self.date,
+            period,
+            budget_names: self.budgets.clone(),
+            output_format,
+            show_all_rows: match (self.show_all_rows, self.no_show_all_rows) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            category_group_watch_list: self.groups.iter().cloned().collect(),
+        }
+    }
 }
 
 pub fn run(api: &dyn YnabApi, cfg: &config::Config) -> Result<()> {
     let budgets = api.get_budgets()?;
-    let budget_id = report::get_budget_id(&budgets, &cfg.budget_name)
-        .ok_or_else(|| anyhow::anyhow!("no budget found with name {}", cfg.budget_name))?;
+    let budget_configs = cfg.budget_configs()?;
+    if let [budget_cfg] = budget_configs.as_slice() {
+        return run_budget(api, cfg, &budgets, budget_cfg, &cfg.output_formats, false);
+    }
+    for budget_cfg in &budget_configs {
+        let output_formats: Vec<OutputFormat> = cfg
+            .output_formats
+            .iter()
+            .map(|output| output.for_budget(&budget_cfg.budget_name))
+            .collect();
+        run_budget(api, cfg, &budgets, budget_cfg, &output_formats, true)?;
+    }
+    Ok(())
+}
+
+/// Reports on one budget, rendering every output from the same fetched data. With
+/// `labelled`, printed output starts with the budget's name so the sections of a
+/// multi-budget run can be told apart.
+fn run_budget(
+    api: &dyn YnabApi,
+    cfg: &config::Config,
+    budgets: &[BudgetSummary],
+    budget_cfg: &config::BudgetConfig,
+    output_formats: &[OutputFormat],
+    labelled: bool,
+) -> Result<()> {
+    let budget = report::get_budget(budgets, &budget_cfg.budget_name)
+        .ok_or_else(|| anyhow::anyhow!("no budget found with name {}", budget_cfg.budget_name))?;
+    let budget_id = budget.id.clone();
+    let currency = cfg
+        .currency_format
+        .apply(budget.currency_format.clone().unwrap_or_default());
 
-    let category_groups = api.get_category_groups(&budget_id)?;
-    let missing =
-        report::get_missing_category_groups(&category_groups, &cfg.category_group_watch_list);
+    let resolution_date = cfg
+        .resolution_date
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    let period =
+        cfg.period
+            .period_for_date(resolution_date, cfg.first_weekday(), cfg.week_numbering)?;
+    let report_start = period.start;
+    let report_end = period.end;
+    let budget_month = period.budget_month(resolution_date);
+
+    let data = api.get_budget_data(&budget_id, budget_month, report_start, cfg.show_net_worth)?;
+    let category_groups = data.category_groups;
+    let missing = report::get_missing_category_groups(
+        &category_groups,
+        &budget_cfg.category_group_watch_list,
+    );
     if !missing.is_empty() {
-        let mut names: Vec<&str> = missing.iter().map(String::as_str).collect();
-        names.sort();
+        let mut keys: Vec<&str> = missing.iter().map(String::as_str).collect();
+        keys.sort();
+        let described: Vec<String> = keys
+            .into_iter()
+            .map(|key| {
+                let suggestions = watch_list::suggestions(&category_groups, key);
+                if suggestions.is_empty() {
+                    key.to_string()
+                } else {
+                    let quoted: Vec<String> =
+                        suggestions.iter().map(|name| format!("{name:?}")).collect();
+                    format!("{key} (did you mean {}?)", quoted.join(" or "))
+                }
+            })
+            .collect();
         eprintln!(
             "Warning: categoryGroupWatchList includes unknown category groups: {}",
-            names.join(", ")
+            described.join(", ")
         );
     }
+    let group_colors =
+        watch_list::resolve(&category_groups, &budget_cfg.category_group_watch_list)?;
 
-    let categories_to_watch =
-        report::get_categories_to_watch(&category_groups, &cfg.category_group_watch_list);
+    let unknown = report::get_unknown_rule_categories(&category_groups, &budget_cfg.category_rules);
+    if !unknown.is_empty() {
+        eprintln!(
+            "Warning: categoryRules names unknown category groups or categories: {}",
+            unknown.join(", ")
+        );
+    }
 
-    let resolution_date = cfg
-        .resolution_date
-        .unwrap_or_else(|| chrono::Local::now().date_naive());
-    let report_week = month_week_for_date(resolution_date)?;
-    let report_start = report_week.week_start;
-    let report_end = report_week.week_end;
+    let category_rules = watch_list::resolve(&category_groups, &budget_cfg.category_rules)?;
 
-    let month_categories: Vec<_> = categories_to_watch
+    let categories_to_watch =
+        report::get_categories_to_watch(&category_groups, &group_colors, &category_rules);
+
+    let other_months: Vec<NaiveDate> = period
+        .months()
+        .into_iter()
+        .filter(|month| {
+            month.year() != budget_month.year() || month.month() != budget_month.month()
+        })
+        .collect();
+    let other_month_categories = api
+        .get_month_details(&budget_id, &other_months)?
         .iter()
-        .map(|cat| api.get_month_category(&budget_id, report_start, &cat.id))
-        .collect::<Result<Vec<_>>>()
-        .context("fetching month categories")?;
+        .map(|month| get_month_categories_for(api, &budget_id, month, &categories_to_watch))
+        .collect::<crustynab::Result<Vec<_>>>()?;
+    let month_categories = report::sum_budgeted_over_months(
+        get_month_categories_for(api, &budget_id, &data.month, &categories_to_watch)?,
+        &other_month_categories,
+    );
 
     let categories_budgeted = report::categories_to_polars(&month_categories)?;
 
-    let transactions = api.get_transactions(&budget_id, report_start)?;
-    let transactions_frame = report::transactions_to_polars(&transactions)?;
-    let transactions_frame =
-        report::relevant_transactions(transactions_frame, report_start, report_end);
+    let month_summary_table = report::build_month_summary_table(&data.month.summary)?;
+
+    let net_worth_table = data
+        .accounts
+        .map(|accounts| -> Result<LazyFrame> {
+            Ok(report::build_net_worth_table(
+                report::accounts_to_polars(&accounts)?,
+                &data.transactions,
+                report_start,
+                report_end,
+            ))
+        })
+        .transpose()?;
+    let transactions_frame = report::transactions_to_polars(&data.transactions)?;
+    let transactions_frame = report::relevant_transactions(transactions_frame, &period);
+
+    let upcoming_frame = report::upcoming_transactions_to_polars(
+        &data.scheduled_transactions,
+        &category_groups,
+        report_start,
+        report_end,
+    )?;
 
     let category_names: HashSet<String> = month_categories.iter().map(|c| c.name.clone()).collect();
 
-    let report_table =
-        report::build_report_table(categories_budgeted, transactions_frame, &category_names)?;
+    let report_table = report::build_report_table(
+        categories_budgeted,
+        transactions_frame,
+        upcoming_frame.clone(),
+        &category_names,
+    )?;
+    let report_table = report::apply_category_rules(report_table, &category_rules)?;
+    let upcoming_table = report::apply_category_rules(
+        report::build_upcoming_table(upcoming_frame, &category_names),
+        &category_rules,
+    )?;
 
     let report_table_full = report_table.clone();
     let report_table_display = if cfg.show_all_rows {
         report_table
     } else {
-        report_table.filter(col("spent").neq(lit(0.0)))
+        report_table.filter(
+            col("spent")
+                .neq(lit(0_i64))
+                .or(col("upcoming").neq(lit(0_i64))),
+        )
     };
 
     let category_group_totals =
         report::build_category_group_totals_table(report_table_full.clone())?;
+    let report_table_display = report::to_display_units(report_table_display, &currency)?;
+    let category_group_totals = report::to_display_units(category_group_totals, &currency)?;
+    let month_summary_display = report::to_display_units(month_summary_table.clone(), &currency)?;
+    let upcoming_display = report::to_display_units(upcoming_table.clone(), &currency)?;
+    let net_worth_display = net_worth_table
+        .clone()
+        .map(|table| report::to_display_units(table, &currency))
+        .transpose()?;
 
-    let week_year = report_week.week_start.year();
-    let week_number = report_week.week_number;
     let start_label = report_start.format("%A %Y-%m-%d");
     let end_label = report_end.format("%A %Y-%m-%d");
+    if labelled {
+        println!("Budget: {}", budget_cfg.budget_name);
+    }
     println!(
-        "Week {week_number} of {week_year}, starting on {start_label} and ending on {end_label}"
+        "{}, starting on {start_label} and ending on {end_label}",
+        period.name
     );
 
-    let week_short_start = format_short_date(report_start);
-    let week_short_end = format_short_date(report_end);
-    let visual_week_label = format!("Week {week_number} ({week_short_start} - {week_short_end})");
-
-    match &cfg.output_format {
-        OutputFormat::Simple(SimpleOutputFormat::PolarsPrint) => {
-            // SAFETY: single-threaded at this point, no concurrent env access
-            unsafe { std::env::set_var("POLARS_FMT_MAX_ROWS", "-1") };
-            let df = report_table_display
-                .collect()
-                .context("collecting report table")?;
-            let totals = category_group_totals
-                .collect()
-                .context("collecting totals")?;
-            println!("{df}");
-            println!("Category group totals");
-            println!("{totals}");
-        }
-        OutputFormat::Simple(SimpleOutputFormat::CsvPrint) => {
-            let mut df = report_table_display
-                .collect()
-                .context("collecting report table")?;
-            let mut totals = category_group_totals
-                .collect()
-                .context("collecting totals")?;
-            let csv = write_csv_string(&mut df)?;
-            let totals_csv = write_csv_string(&mut totals)?;
-            print!("{csv}");
-            println!("category_group_totals");
-            print!("{totals_csv}");
-        }
-        OutputFormat::CsvFile { csv_output } => {
-            let mut df = report_table_display
-                .collect()
-                .context("collecting report table")?;
-            let mut totals = category_group_totals
-                .collect()
-                .context("collecting totals")?;
-            let csv = write_csv_string(&mut df)?;
-            let totals_csv = write_csv_string(&mut totals)?;
-
-            let stem = csv_output
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("report");
-            let ext = csv_output
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("csv");
-            let totals_path =
-                csv_output.with_file_name(format!("{stem}_category_group_totals.{ext}"));
-
-            std::fs::write(csv_output, &csv).with_context(|| format!("writing {csv_output:?}"))?;
-            std::fs::write(&totals_path, &totals_csv)
-                .with_context(|| format!("writing {totals_path:?}"))?;
-        }
-        OutputFormat::VisualFile { visual_output } => {
-            let html = build_visual_report_html(
-                report_table_full,
-                &cfg.category_group_watch_list,
-                &visual_week_label,
-                week_year,
-                cfg.show_all_rows,
-            )?;
-            std::fs::write(visual_output, &html)
-                .with_context(|| format!("writing {visual_output:?}"))?;
+    let short_start = format_short_date(report_start);
+    let short_end = format_short_date(report_end);
+    let visual_label = format!("{} ({short_start} - {short_end})", period.short_name);
+
+    for output_format in output_formats {
+        match output_format {
+            OutputFormat::Simple(SimpleOutputFormat::PolarsPrint) => {
+                let month_summary = month_summary_display
+                    .clone()
+                    .collect()
+                    .context("collecting month summary")?;
+                let df = report_table_display
+                    .clone()
+                    .collect()
+                    .context("collecting report table")?;
+                let totals = category_group_totals
+                    .clone()
+                    .collect()
+                    .context("collecting totals")?;
+                println!("Budget month");
+                println!("{month_summary}");
+                println!("{df}");
+                println!("Category group totals");
+                println!("{totals}");
+                let upcoming = upcoming_display
+                    .clone()
+                    .collect()
+                    .context("collecting upcoming transactions")?;
+                println!("Upcoming in period");
+                println!("{upcoming}");
+                if let Some(net_worth) = &net_worth_display {
+                    let net_worth = net_worth
+                        .clone()
+                        .collect()
+                        .context("collecting net worth")?;
+                    println!("Net worth");
+                    println!("{net_worth}");
+                }
+            }
+            OutputFormat::Simple(SimpleOutputFormat::CsvPrint) => {
+                let mut df = report_table_display
+                    .clone()
+                    .collect()
+                    .context("collecting report table")?;
+                let mut totals = category_group_totals
+                    .clone()
+                    .collect()
+                    .context("collecting totals")?;
+                let csv = report::write_csv_string(&mut df)?;
+                let totals_csv = report::write_csv_string(&mut totals)?;
+                let mut upcoming = upcoming_display
+                    .clone()
+                    .collect()
+                    .context("collecting upcoming transactions")?;
+                let upcoming_csv = report::write_csv_string(&mut upcoming)?;
+                let mut month_summary = month_summary_display
+                    .clone()
+                    .collect()
+                    .context("collecting month summary")?;
+                println!("budget_month");
+                print!("{}", report::write_csv_string(&mut month_summary)?);
+                print!("{csv}");
+                println!("category_group_totals");
+                print!("{totals_csv}");
+                println!("upcoming_in_period");
+                print!("{upcoming_csv}");
+                if let Some(net_worth) = &net_worth_display {
+                    let mut net_worth = net_worth
+                        .clone()
+                        .collect()
+                        .context("collecting net worth")?;
+                    println!("net_worth");
+                    print!("{}", report::write_csv_string(&mut net_worth)?);
+                }
+            }
+            OutputFormat::CsvFile { csv_output } => {
+                let mut df = report_table_display
+                    .clone()
+                    .collect()
+                    .context("collecting report table")?;
+                let mut totals = category_group_totals
+                    .clone()
+                    .collect()
+                    .context("collecting totals")?;
+                let mut upcoming = upcoming_display
+                    .clone()
+                    .collect()
+                    .context("collecting upcoming transactions")?;
+                let mut month_summary = month_summary_display
+                    .clone()
+                    .collect()
+                    .context("collecting month summary")?;
+                let csv = report::write_csv_string(&mut df)?;
+                let totals_csv = report::write_csv_string(&mut totals)?;
+                let upcoming_csv = report::write_csv_string(&mut upcoming)?;
+                let month_summary_csv = report::write_csv_string(&mut month_summary)?;
+
+                let totals_path = csv_section_path(csv_output, "category_group_totals");
+                let upcoming_path = csv_section_path(csv_output, "upcoming");
+                let month_summary_path = csv_section_path(csv_output, "budget_month");
+
+                std::fs::write(csv_output, &csv)
+                    .with_context(|| format!("writing {csv_output:?}"))?;
+                std::fs::write(&totals_path, &totals_csv)
+                    .with_context(|| format!("writing {totals_path:?}"))?;
+                std::fs::write(&upcoming_path, &upcoming_csv)
+                    .with_context(|| format!("writing {upcoming_path:?}"))?;
+                std::fs::write(&month_summary_path, &month_summary_csv)
+                    .with_context(|| format!("writing {month_summary_path:?}"))?;
+                if let Some(net_worth) = &net_worth_display {
+                    let mut net_worth = net_worth
+                        .clone()
+                        .collect()
+                        .context("collecting net worth")?;
+                    let net_worth_path = csv_section_path(csv_output, "net_worth");
+                    std::fs::write(&net_worth_path, report::write_csv_string(&mut net_worth)?)
+                        .with_context(|| format!("writing {net_worth_path:?}"))?;
+                }
+            }
+            OutputFormat::VisualFile { visual_output } => {
+                let html = build_visual_report_html(
+                    report_table_full.clone(),
+                    ReportSections {
+                        month_summary: Some(month_summary_table.clone()),
+                        upcoming: Some(upcoming_table.clone()),
+                        net_worth: net_worth_table.clone(),
+                    },
+                    &group_colors,
+                    &visual_label,
+                    PlannedYear {
+                        year: budget_month.year(),
+                        budgeted_months: period.months().len() as u32,
+                    },
+                    cfg.show_all_rows,
+                    &currency,
+                )?;
+                std::fs::write(visual_output, &html)
+                    .with_context(|| format!("writing {visual_output:?}"))?;
+            }
         }
     }
 
     Ok(())
 }
 
-fn write_csv_string(df: &mut DataFrame) -> Result<String> {
-    let mut buf = Vec::new();
-    CsvWriter::new(&mut buf).finish(df).context("writing CSV")?;
-    String::from_utf8(buf).context("CSV not valid UTF-8")
-}
-
 fn format_short_date(date: chrono::NaiveDate) -> String {
     let formatted = date.format("%b %d").to_string();
     if let Some(space_pos) = formatted.rfind(' ') {
         let (prefix, day_part) = formatted.split_at(space_pos + 1);
-        if day_part.starts_with('0') {
-            return format!("{}{}", prefix, &day_part[1..]);
+        if let Some(day) = day_part.strip_prefix('0') {
+            return format!("{prefix}{day}");
         }
     }
     formatted
 }
 
 fn main() -> Result<()> {
+    // Print every row of the polars tables. SAFETY: this runs first thing in `main`, before
+    // polars, tokio or anything else has started a thread that could read the environment.
+    unsafe { std::env::set_var("POLARS_FMT_MAX_ROWS", "-1") };
     let args = Args::parse();
-    let cfg = config::load_config(&args.config)?;
-    let api = HttpYnabClient::new(&cfg.personal_access_token)?;
-    run(&api, &cfg)
+    let cfg = config::load_config_with_overrides(&args.config, &args.config_overrides())?;
+    for warning in cfg.warnings() {
+        eprintln!("Warning: {warning}");
+    }
+    let http = || -> Result<HttpYnabClient> {
+        Ok(HttpYnabClient::new(cfg.personal_access_token()?.expose())?
+            .with_max_concurrent_requests(args.max_concurrent_requests)
+            .with_retry_policy(RetryPolicy {
+                max_retries: args.max_retries,
+                ..RetryPolicy::default()
+            }))
+    };
+    let source: Box<dyn YnabApi> = match args.replay {
+        Some(replay_dir) => Box::new(ReplayYnabApi::new(replay_dir)),
+        None => Box::new(http()?),
+    };
+    let cached = args
+        .cache_dir
+        .map(|cache_dir| CachingYnabApi::new(source.as_ref(), cache_dir));
+    let api: &dyn YnabApi = match &cached {
+        Some(cached) => cached,
+        None => source.as_ref(),
+    };
+    // The recorder goes outside the cache so cache hits are recorded too.
+    let result = match args.record {
+        Some(record_dir) => run(&RecordingYnabApi::new(api, record_dir), &cfg),
+        None => run(api, &cfg),
+    };
+    for warning in cached.iter().flat_map(CachingYnabApi::take_warnings) {
+        eprintln!("Warning: {warning}");
+    }
+    result
 }
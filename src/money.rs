@@ -0,0 +1,150 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+/// An amount of money in YNAB milliunits: thousandths of the budget's currency unit.
+/// Amounts stay in milliunits through every calculation so totals match YNAB exactly,
+/// and are only turned into currency units for display.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Milliunits(pub i64);
+
+impl Milliunits {
+    pub const ZERO: Self = Self(0);
+
+    /// The amount in currency units, for display only.
+    pub fn to_units(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+impl From<i64> for Milliunits {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+impl Add for Milliunits {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Milliunits {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Milliunits {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Milliunits {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul<i64> for Milliunits {
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Sum for Milliunits {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|m| m.0).sum())
+    }
+}
+
+impl fmt::Display for Milliunits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:03}", abs / 1000, abs % 1000)
+    }
+}
+
+/// How a budget writes amounts, as set in YNAB's budget settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrencyFormat {
+    #[serde(default)]
+    pub iso_code: String,
+    pub decimal_digits: u32,
+    pub decimal_separator: String,
+    pub symbol_first: bool,
+    pub group_separator: String,
+    pub currency_symbol: String,
+    pub display_symbol: bool,
+}
+
+impl Default for CurrencyFormat {
+    fn default() -> Self {
+        Self {
+            iso_code: "GBP".to_string(),
+            decimal_digits: 2,
+            decimal_separator: ".".to_string(),
+            symbol_first: true,
+            group_separator: ",".to_string(),
+            currency_symbol: "£".to_string(),
+            display_symbol: true,
+        }
+    }
+}
+
+impl CurrencyFormat {
+    /// Rounds `value` (in currency units) to this currency's decimal digits.
+    pub fn round(&self, value: f64) -> f64 {
+        let scale = 10_f64.powi(self.decimal_digits as i32);
+        (value * scale).round() / scale
+    }
+
+    /// Writes `value` (in currency units) with this currency's symbol, separators and
+    /// decimal digits, e.g. `-£1,234.50` or `1.234,50€`.
+    pub fn format(&self, value: f64) -> String {
+        let rounded = self.round(value);
+        let sign = if rounded < 0.0 { "-" } else { "" };
+        let digits = self.decimal_digits as usize;
+        let fixed = format!("{:.digits$}", rounded.abs());
+        let (integer_part, decimal_part) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+        let mut number = group_thousands(integer_part, &self.group_separator);
+        if !decimal_part.is_empty() {
+            number.push_str(&self.decimal_separator);
+            number.push_str(decimal_part);
+        }
+
+        match (self.display_symbol, self.symbol_first) {
+            (false, _) => format!("{sign}{number}"),
+            (true, true) => format!("{sign}{}{number}", self.currency_symbol),
+            (true, false) => format!("{sign}{number}{}", self.currency_symbol),
+        }
+    }
+}
+
+fn group_thousands(digits: &str, separator: &str) -> String {
+    let len = digits.len();
+    let mut grouped = String::with_capacity(len + len / 3 * separator.len());
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -1,85 +1,268 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use anyhow::{Context, Result};
 use chrono::NaiveDate;
+use indexmap::IndexMap;
 use polars::prelude::*;
 
-use crate::ynab::{BudgetSummary, Category, CategoryGroup, Transaction};
+use crate::calendar_weeks::Period;
+use crate::config::CategoryRules;
+use crate::error::{Error, Result, ResultExt};
+use crate::money::{CurrencyFormat, Milliunits};
+use crate::watch_list::GroupPattern;
+use crate::ynab::{
+    Account, BudgetSummary, Category, CategoryGroup, MonthSummary, ScheduledTransaction,
+    Transaction, category_names_by_id,
+};
 
 // --- Newtypes for DataFrames ---
 
+/// Money columns hold exact milliunits as `i64` until [`to_display_units`] turns them into
+/// currency units for output.
+pub const MONEY_COLUMNS: [&str; 18] = [
+    "amount",
+    "to_be_budgeted",
+    "income",
+    "activity",
+    "budgeted",
+    "spent",
+    "upcoming",
+    "balance",
+    "cleared_balance",
+    "uncleared_balance",
+    "assets",
+    "liabilities",
+    "net_worth",
+    "net_worth_change",
+    "goal_target",
+    "goal_per_year",
+    "goal_under_funded",
+    "goal_overall_left",
+];
+
 #[derive(Clone)]
 pub struct CategoryFrame(pub LazyFrame);
 
 #[derive(Clone)]
 pub struct TransactionFrame(pub LazyFrame);
 
+#[derive(Clone)]
+pub struct AccountFrame(pub LazyFrame);
+
 // --- Pure functions ---
 
+pub fn get_budget<'a>(
+    budgets: &'a [BudgetSummary],
+    budget_name: &str,
+) -> Option<&'a BudgetSummary> {
+    budgets.iter().find(|b| b.name == budget_name)
+}
+
 pub fn get_budget_id(budgets: &[BudgetSummary], budget_name: &str) -> Option<String> {
-    budgets
-        .iter()
-        .find(|b| b.name == budget_name)
-        .map(|b| b.id.clone())
+    get_budget(budgets, budget_name).map(|b| b.id.clone())
 }
 
+/// The watch list keys that match none of `groups`, see [`GroupPattern`] for how keys match.
 pub fn get_missing_category_groups(
     groups: &[CategoryGroup],
-    watch_list: &indexmap::IndexMap<String, String>,
+    watch_list: &IndexMap<String, String>,
 ) -> HashSet<String> {
-    let available: HashSet<&str> = groups.iter().map(|g| g.name.as_str()).collect();
     watch_list
         .keys()
-        .filter(|name| !available.contains(name.as_str()))
+        .filter(|key| match GroupPattern::parse(key) {
+            Ok(pattern) => !groups.iter().any(|g| pattern.matches(g)),
+            Err(_) => true,
+        })
         .cloned()
         .collect()
 }
 
+/// The categories to report on: every visible category of a watched group except those its
+/// rules exclude, plus the categories the rules include from unwatched groups. `watch_list`
+/// and `rules` are keyed by group name, as [`crate::watch_list::resolve`] returns them.
 pub fn get_categories_to_watch(
     groups: &[CategoryGroup],
-    watch_list: &indexmap::IndexMap<String, String>,
+    watch_list: &IndexMap<String, String>,
+    rules: &IndexMap<String, CategoryRules>,
 ) -> Vec<Category> {
-    let watched_names: HashSet<&str> = watch_list.keys().map(String::as_str).collect();
+    let no_rules = CategoryRules::default();
     groups
         .iter()
-        .filter(|g| watched_names.contains(g.name.as_str()))
-        .flat_map(|g| g.categories.iter())
+        .flat_map(|g| {
+            let group_rules = rules.get(&g.name).unwrap_or(&no_rules);
+            let watched = watch_list.contains_key(&g.name);
+            g.categories.iter().filter(move |c| {
+                if watched {
+                    !group_rules.exclude.contains(&c.name)
+                } else {
+                    group_rules.include.contains(&c.name)
+                }
+            })
+        })
         .filter(|c| !c.hidden)
         .cloned()
         .collect()
 }
 
+/// Keys of `rules` that match no category group, and categories they name that none of
+/// their groups have, written as `Key` or `Key/Category`, sorted. See [`GroupPattern`] for
+/// how keys match.
+pub fn get_unknown_rule_categories(
+    groups: &[CategoryGroup],
+    rules: &IndexMap<String, CategoryRules>,
+) -> Vec<String> {
+    let mut unknown: Vec<String> = rules
+        .iter()
+        .flat_map(|(key, group_rules)| {
+            let matched: Vec<&CategoryGroup> = match GroupPattern::parse(key) {
+                Ok(pattern) => groups.iter().filter(|g| pattern.matches(g)).collect(),
+                Err(_) => Vec::new(),
+            };
+            if matched.is_empty() {
+                return vec![key.clone()];
+            }
+            let named = group_rules
+                .exclude
+                .iter()
+                .chain(&group_rules.include)
+                .chain(group_rules.aliases.keys())
+                .chain(&group_rules.order);
+            named
+                .filter(|name| {
+                    !matched
+                        .iter()
+                        .any(|g| g.categories.iter().any(|c| &c.name == *name))
+                })
+                .map(|name| format!("{key}/{name}"))
+                .collect()
+        })
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    unknown
+}
+
+/// Renames and reorders the categories of `table` as `rules` say. Rows are sorted by group,
+/// then by each group's `order`, then by the YNAB category name; tables without a
+/// `category_group_name` column keep their row order. Rules only apply to their own group,
+/// except in tables without a group column, where a category name listed by several groups
+/// gets the first group's rules. `rules` is keyed by group name, as
+/// [`crate::watch_list::resolve`] returns it. Apply this after every join on
+/// `category_name`, since aliases replace it.
+pub fn apply_category_rules(
+    mut table: LazyFrame,
+    rules: &IndexMap<String, CategoryRules>,
+) -> Result<LazyFrame> {
+    let schema = table
+        .collect_schema()
+        .shape_context("reading table schema")?;
+    let by_group = schema.contains("category_group_name");
+
+    let mut groups: Vec<&str> = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    let mut aliases: Vec<Option<&str>> = Vec::new();
+    let mut positions: Vec<Option<u32>> = Vec::new();
+    for (group_name, group_rules) in rules {
+        let listed = group_rules.order.iter().chain(group_rules.aliases.keys());
+        for name in listed {
+            let seen = names
+                .iter()
+                .zip(&groups)
+                .any(|(n, g)| *n == name.as_str() && (!by_group || *g == group_name.as_str()));
+            if seen {
+                continue;
+            }
+            groups.push(group_name);
+            names.push(name);
+            aliases.push(group_rules.aliases.get(name).map(String::as_str));
+            positions.push(
+                group_rules
+                    .order
+                    .iter()
+                    .position(|n| n == name)
+                    .map(|p| p as u32),
+            );
+        }
+    }
+    let mut rule_columns = vec![
+        Column::new("category_name".into(), &names),
+        Column::new("_alias".into(), &aliases),
+        Column::new("_position".into(), &positions),
+    ];
+    let mut keys = vec![col("category_name")];
+    if by_group {
+        rule_columns.push(Column::new("category_group_name".into(), &groups));
+        keys.push(col("category_group_name"));
+    }
+    let rules_df =
+        DataFrame::new(rule_columns).shape_context("building category rules DataFrame")?;
+
+    let columns: Vec<Expr> = schema.iter_names().map(|name| col(name.clone())).collect();
+    let joined = table
+        .with_row_index("_row", None)
+        .join(
+            rules_df.lazy(),
+            keys.clone(),
+            keys,
+            JoinArgs::new(JoinType::Left),
+        )
+        .with_column(col("_position").fill_null(lit(u32::MAX)));
+    let sorted = if by_group {
+        joined.sort(
+            ["category_group_name", "_position", "category_name"],
+            SortMultipleOptions::default(),
+        )
+    } else {
+        joined.sort(["_row"], SortMultipleOptions::default())
+    };
+    Ok(sorted
+        .with_column(
+            col("_alias")
+                .fill_null(col("category_name"))
+                .alias("category_name"),
+        )
+        .select(columns))
+}
+
 fn date_to_polars_days(date: NaiveDate) -> i32 {
     let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid epoch");
     (date - epoch).num_days() as i32
 }
 
-struct TransactionRow {
-    date: NaiveDate,
-    amount: f64,
-    payee_name: Option<String>,
-    category_name: String,
+/// One row of a [`TransactionFrame`]: a whole transaction, or one category line of a split.
+/// Account, approval and flag fields always come from the parent transaction.
+struct TransactionRow<'a> {
+    txn: &'a Transaction,
+    amount: Milliunits,
+    memo: Option<&'a str>,
+    payee_name: Option<&'a str>,
+    category_name: &'a str,
+    transfer_account_id: Option<&'a str>,
 }
 
-fn expand_transaction(txn: &Transaction) -> Vec<TransactionRow> {
+fn expand_transaction(txn: &Transaction) -> Vec<TransactionRow<'_>> {
     if !txn.subtransactions.is_empty() {
         txn.subtransactions
             .iter()
+            .filter(|sub| !sub.deleted)
             .filter_map(|sub| {
-                sub.category_name.as_ref().map(|cat_name| TransactionRow {
-                    date: txn.date,
-                    amount: sub.amount as f64 / 1000.0,
-                    payee_name: sub.payee_name.clone().or_else(|| txn.payee_name.clone()),
-                    category_name: cat_name.clone(),
+                sub.category_name.as_deref().map(|cat_name| TransactionRow {
+                    txn,
+                    amount: sub.amount,
+                    memo: sub.memo.as_deref().or(txn.memo.as_deref()),
+                    payee_name: sub.payee_name.as_deref().or(txn.payee_name.as_deref()),
+                    category_name: cat_name,
+                    transfer_account_id: sub.transfer_account_id.as_deref(),
                 })
             })
             .collect()
     } else if let Some(cat_name) = &txn.category_name {
         vec![TransactionRow {
-            date: txn.date,
-            amount: txn.amount as f64 / 1000.0,
-            payee_name: txn.payee_name.clone(),
-            category_name: cat_name.clone(),
+            txn,
+            amount: txn.amount,
+            memo: txn.memo.as_deref(),
+            payee_name: txn.payee_name.as_deref(),
+            category_name: cat_name,
+            transfer_account_id: txn.transfer_account_id.as_deref(),
         }]
     } else {
         vec![]
@@ -89,69 +272,190 @@ fn expand_transaction(txn: &Transaction) -> Vec<TransactionRow> {
 pub fn transactions_to_polars(transactions: &[Transaction]) -> Result<TransactionFrame> {
     let rows: Vec<TransactionRow> = transactions.iter().flat_map(expand_transaction).collect();
 
-    let dates: Vec<i32> = rows.iter().map(|r| date_to_polars_days(r.date)).collect();
-    let amounts: Vec<f64> = rows.iter().map(|r| r.amount).collect();
-    let payees: Vec<Option<&str>> = rows.iter().map(|r| r.payee_name.as_deref()).collect();
-    let categories: Vec<&str> = rows.iter().map(|r| r.category_name.as_str()).collect();
+    let dates: Vec<i32> = rows
+        .iter()
+        .map(|r| date_to_polars_days(r.txn.date))
+        .collect();
+    let amounts: Vec<i64> = rows.iter().map(|r| r.amount.0).collect();
+    let payees: Vec<Option<&str>> = rows.iter().map(|r| r.payee_name).collect();
+    let categories: Vec<&str> = rows.iter().map(|r| r.category_name).collect();
+    let account_ids: Vec<&str> = rows.iter().map(|r| r.txn.account_id.as_str()).collect();
+    let account_names: Vec<&str> = rows.iter().map(|r| r.txn.account_name.as_str()).collect();
+    let memos: Vec<Option<&str>> = rows.iter().map(|r| r.memo).collect();
+    let cleared: Vec<&str> = rows.iter().map(|r| r.txn.cleared.as_str()).collect();
+    let approved: Vec<bool> = rows.iter().map(|r| r.txn.approved).collect();
+    let flag_colors: Vec<Option<&str>> = rows.iter().map(|r| r.txn.flag_color.as_deref()).collect();
+    let transfer_accounts: Vec<Option<&str>> = rows.iter().map(|r| r.transfer_account_id).collect();
+    let import_ids: Vec<Option<&str>> = rows.iter().map(|r| r.txn.import_id.as_deref()).collect();
+    let deleted: Vec<bool> = rows.iter().map(|r| r.txn.deleted).collect();
 
     let date_series = Column::new("date".into(), &dates)
         .cast(&DataType::Date)
-        .context("casting date column")?;
+        .shape_context("casting date column")?;
     let df = DataFrame::new(vec![
         date_series,
         Column::new("amount".into(), &amounts),
         Column::new("payee_name".into(), &payees),
         Column::new("category_name".into(), &categories),
+        Column::new("account_id".into(), &account_ids),
+        Column::new("account_name".into(), &account_names),
+        Column::new("memo".into(), &memos),
+        Column::new("cleared".into(), &cleared),
+        Column::new("approved".into(), &approved),
+        Column::new("flag_color".into(), &flag_colors),
+        Column::new("transfer_account_id".into(), &transfer_accounts),
+        Column::new("import_id".into(), &import_ids),
+        Column::new("deleted".into(), &deleted),
     ])
-    .context("building transactions DataFrame")?;
+    .shape_context("building transactions DataFrame")?;
 
     Ok(TransactionFrame(df.lazy()))
 }
 
+/// Every occurrence of `scheduled` from `start` to `end` that has not been entered yet, as the
+/// transactions YNAB will enter for them. Split lines get their category names from `groups`.
+pub fn upcoming_transactions_to_polars(
+    scheduled: &[ScheduledTransaction],
+    groups: &[CategoryGroup],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<TransactionFrame> {
+    let category_names = category_names_by_id(groups);
+    let upcoming: Vec<Transaction> = scheduled
+        .iter()
+        .filter(|s| !s.deleted)
+        .flat_map(|s| {
+            s.occurrences(start, end)
+                .into_iter()
+                .map(|date| s.on(date, &category_names))
+        })
+        .collect();
+    transactions_to_polars(&upcoming)
+}
+
+/// `categories` of the budget month with the `budgeted` of the same categories in
+/// `other_months` added, for periods that touch several budget months. Balances and goals
+/// stay those of the budget month.
+pub fn sum_budgeted_over_months(
+    mut categories: Vec<Category>,
+    other_months: &[Vec<Category>],
+) -> Vec<Category> {
+    for month in other_months {
+        let budgeted: HashMap<&str, Milliunits> =
+            month.iter().map(|c| (c.id.as_str(), c.budgeted)).collect();
+        for category in &mut categories {
+            if let Some(amount) = budgeted.get(category.id.as_str()) {
+                category.budgeted += *amount;
+            }
+        }
+    }
+    categories
+}
+
 pub fn categories_to_polars(categories: &[Category]) -> Result<CategoryFrame> {
     let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
     let group_names: Vec<&str> = categories
         .iter()
         .map(|c| c.category_group_name.as_deref().unwrap_or("Uncategorized"))
         .collect();
-    let budgeted: Vec<f64> = categories
-        .iter()
-        .map(|c| c.budgeted as f64 / 1000.0)
-        .collect();
-    let balance: Vec<f64> = categories
-        .iter()
-        .map(|c| c.balance as f64 / 1000.0)
-        .collect();
+    let budgeted: Vec<i64> = categories.iter().map(|c| c.budgeted.0).collect();
+    let balance: Vec<i64> = categories.iter().map(|c| c.balance.0).collect();
     let goal_cadence: Vec<&str> = categories
         .iter()
         .map(|c| {
-            if c.goal_target.is_some() && c.goal_cadence == Some(1) {
+            if c.has_monthly_goal() {
                 "monthly"
             } else {
                 "annual"
             }
         })
         .collect();
+    let goals_per_year: Vec<Option<i64>> = categories
+        .iter()
+        .map(|c| c.goal_per_year().map(|m| m.0))
+        .collect();
+    let goal_types: Vec<Option<&str>> = categories.iter().map(|c| c.goal_type.as_deref()).collect();
+    let goal_frequencies: Vec<Option<i32>> = categories
+        .iter()
+        .map(|c| c.goal_cadence_frequency)
+        .collect();
+    let goal_targets: Vec<Option<i64>> = categories
+        .iter()
+        .map(|c| c.goal_target.map(|m| m.0))
+        .collect();
+    let goal_target_months: Vec<Option<i32>> = categories
+        .iter()
+        .map(|c| c.goal_target_month.map(date_to_polars_days))
+        .collect();
+    let goal_percentages: Vec<Option<i32>> = categories
+        .iter()
+        .map(|c| c.goal_percentage_complete)
+        .collect();
+    let goal_under_funded: Vec<Option<i64>> = categories
+        .iter()
+        .map(|c| c.goal_under_funded.map(|m| m.0))
+        .collect();
+    let goal_overall_left: Vec<Option<i64>> = categories
+        .iter()
+        .map(|c| c.goal_overall_left.map(|m| m.0))
+        .collect();
+    let goal_months_to_budget: Vec<Option<i32>> =
+        categories.iter().map(|c| c.goal_months_to_budget).collect();
 
+    let goal_target_month = Column::new("goal_target_month".into(), &goal_target_months)
+        .cast(&DataType::Date)
+        .shape_context("casting goal_target_month column")?;
     let df = DataFrame::new(vec![
         Column::new("category_name".into(), &names),
         Column::new("category_group_name".into(), &group_names),
         Column::new("budgeted".into(), &budgeted),
         Column::new("balance".into(), &balance),
         Column::new("goal_cadence".into(), &goal_cadence),
+        Column::new("goal_type".into(), &goal_types),
+        Column::new("goal_cadence_frequency".into(), &goal_frequencies),
+        Column::new("goal_target".into(), &goal_targets),
+        Column::new("goal_per_year".into(), &goals_per_year),
+        goal_target_month,
+        Column::new("goal_percentage_complete".into(), &goal_percentages),
+        Column::new("goal_under_funded".into(), &goal_under_funded),
+        Column::new("goal_overall_left".into(), &goal_overall_left),
+        Column::new("goal_months_to_budget".into(), &goal_months_to_budget),
     ])
-    .context("building categories DataFrame")?;
+    .shape_context("building categories DataFrame")?;
 
     Ok(CategoryFrame(df.lazy()))
 }
 
-pub fn relevant_transactions(
-    tf: TransactionFrame,
-    start_date: NaiveDate,
-    end_date: NaiveDate,
-) -> TransactionFrame {
-    let start = date_to_polars_days(start_date);
-    let end = date_to_polars_days(end_date);
+pub fn accounts_to_polars(accounts: &[Account]) -> Result<AccountFrame> {
+    let live: Vec<&Account> = accounts.iter().filter(|a| !a.deleted).collect();
+    let names: Vec<&str> = live.iter().map(|a| a.name.as_str()).collect();
+    let types: Vec<&str> = live.iter().map(|a| a.account_type.as_str()).collect();
+    let liabilities: Vec<bool> = live.iter().map(|a| a.account_type.is_liability()).collect();
+    let on_budget: Vec<bool> = live.iter().map(|a| a.on_budget).collect();
+    let closed: Vec<bool> = live.iter().map(|a| a.closed).collect();
+    let balance: Vec<i64> = live.iter().map(|a| a.balance.0).collect();
+    let cleared: Vec<i64> = live.iter().map(|a| a.cleared_balance.0).collect();
+    let uncleared: Vec<i64> = live.iter().map(|a| a.uncleared_balance.0).collect();
+
+    let df = DataFrame::new(vec![
+        Column::new("account_name".into(), &names),
+        Column::new("account_type".into(), &types),
+        Column::new("is_liability".into(), &liabilities),
+        Column::new("on_budget".into(), &on_budget),
+        Column::new("closed".into(), &closed),
+        Column::new("balance".into(), &balance),
+        Column::new("cleared_balance".into(), &cleared),
+        Column::new("uncleared_balance".into(), &uncleared),
+    ])
+    .shape_context("building accounts DataFrame")?;
+
+    Ok(AccountFrame(df.lazy()))
+}
+
+/// The transactions dated within `period`.
+pub fn relevant_transactions(tf: TransactionFrame, period: &Period) -> TransactionFrame {
+    let start = date_to_polars_days(period.start);
+    let end = date_to_polars_days(period.end);
     TransactionFrame(
         tf.0.filter(
             col("date")
@@ -162,43 +466,138 @@ pub fn relevant_transactions(
     )
 }
 
-pub fn build_report_table(
-    categories: CategoryFrame,
-    transactions: TransactionFrame,
-    category_names: &HashSet<String>,
-) -> Result<LazyFrame> {
+fn category_name_filter(category_names: &HashSet<String>) -> Expr {
     let names_vec: Vec<&str> = category_names.iter().map(String::as_str).collect();
     let names_series = Series::new("_cat_filter".into(), &names_vec);
+    col("category_name").is_in(lit(names_series))
+}
 
-    let total_spent = transactions
+fn sum_by_category(
+    transactions: TransactionFrame,
+    category_names: &HashSet<String>,
+    alias: &str,
+) -> LazyFrame {
+    transactions
         .0
-        .filter(col("category_name").is_in(lit(names_series)))
+        .filter(category_name_filter(category_names))
         .group_by([col("category_name")])
-        .agg([col("amount").sum().alias("spent")]);
+        .agg([col("amount").sum().alias(alias)])
+}
 
-    let report = categories
-        .0
-        .join(
-            total_spent,
+/// One row per category with what was spent in the period and what scheduled transactions
+/// will still take out of it (`upcoming`), both as signed amounts like `amount`.
+pub fn build_report_table(
+    categories: CategoryFrame,
+    transactions: TransactionFrame,
+    upcoming: TransactionFrame,
+    category_names: &HashSet<String>,
+) -> Result<LazyFrame> {
+    let join_on_category = |frame: LazyFrame, totals: LazyFrame| {
+        frame.join(
+            totals,
             [col("category_name")],
             [col("category_name")],
             JoinArgs::new(JoinType::Left),
         )
-        .with_columns([col("spent").fill_null(lit(0.0))])
+    };
+    let with_spent = join_on_category(
+        categories.0,
+        sum_by_category(transactions, category_names, "spent"),
+    );
+    let report = join_on_category(
+        with_spent,
+        sum_by_category(upcoming, category_names, "upcoming"),
+    )
+    .with_columns([
+        col("spent").fill_null(lit(0_i64)),
+        col("upcoming").fill_null(lit(0_i64)),
+    ])
+    .select([
+        col("category_group_name"),
+        col("category_name"),
+        col("budgeted"),
+        col("spent"),
+        col("upcoming"),
+        col("balance"),
+        col("goal_cadence"),
+        col("goal_per_year"),
+    ])
+    .sort(
+        ["category_group_name", "category_name"],
+        SortMultipleOptions::default(),
+    );
+
+    Ok(report)
+}
+
+/// The scheduled transactions still to come in watched categories, in date order.
+pub fn build_upcoming_table(
+    upcoming: TransactionFrame,
+    category_names: &HashSet<String>,
+) -> LazyFrame {
+    upcoming
+        .0
+        .filter(category_name_filter(category_names))
         .select([
-            col("category_group_name"),
+            col("date"),
+            col("payee_name"),
             col("category_name"),
-            col("budgeted"),
-            col("spent"),
-            col("balance"),
-            col("goal_cadence"),
+            col("account_name"),
+            col("memo"),
+            col("amount"),
         ])
         .sort(
-            ["category_group_name", "category_name"],
+            ["date", "category_name", "payee_name"],
             SortMultipleOptions::default(),
-        );
+        )
+}
 
-    Ok(report)
+/// The budget-month header: one row with Ready to Assign, income, activity and budgeted for
+/// the whole budget, and the age of money in days.
+pub fn build_month_summary_table(summary: &MonthSummary) -> Result<LazyFrame> {
+    let month = Column::new("month".into(), [date_to_polars_days(summary.month)])
+        .cast(&DataType::Date)
+        .shape_context("casting month column")?;
+    let df = DataFrame::new(vec![
+        month,
+        Column::new("to_be_budgeted".into(), [summary.to_be_budgeted.0]),
+        Column::new("income".into(), [summary.income.0]),
+        Column::new("activity".into(), [summary.activity.0]),
+        Column::new("budgeted".into(), [summary.budgeted.0]),
+        Column::new("age_of_money".into(), [summary.age_of_money]),
+    ])
+    .shape_context("building month summary DataFrame")?;
+    Ok(df.lazy())
+}
+
+/// A one-row summary of what the budget's accounts are worth now: assets, liabilities (as a
+/// negative amount), their sum, and how much that sum changed from `start` to `end`.
+/// `transactions` must include transfers and uncategorised transactions, which is why they
+/// are taken directly rather than as a [`TransactionFrame`].
+pub fn build_net_worth_table(
+    accounts: AccountFrame,
+    transactions: &[Transaction],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> LazyFrame {
+    let change: Milliunits = transactions
+        .iter()
+        .filter(|t| !t.deleted && start <= t.date && t.date <= end)
+        .map(|t| t.amount)
+        .sum();
+    let sum_where = |liability: bool| {
+        col("balance")
+            .filter(col("is_liability").eq(lit(liability)))
+            .sum()
+    };
+    accounts.0.select([
+        sum_where(false).alias("assets"),
+        sum_where(true).alias("liabilities"),
+        col("balance").sum().alias("net_worth"),
+        lit(change.0)
+            .cast(DataType::Int64)
+            .alias("net_worth_change"),
+    ])
 }
 
 pub fn build_category_group_totals_table(report_table: LazyFrame) -> Result<LazyFrame> {
@@ -208,12 +607,14 @@ pub fn build_category_group_totals_table(report_table: LazyFrame) -> Result<Lazy
         .agg([
             col("budgeted").sum().alias("budgeted"),
             col("spent").sum().alias("spent"),
+            col("upcoming").sum().alias("upcoming"),
             col("balance").sum().alias("balance"),
         ])
         .select([
             col("category_group_name"),
             col("budgeted"),
             col("spent"),
+            col("upcoming"),
             col("balance"),
         ])
         .sort(["category_group_name"], SortMultipleOptions::default());
@@ -222,11 +623,39 @@ pub fn build_category_group_totals_table(report_table: LazyFrame) -> Result<Lazy
         lit("Total").alias("category_group_name"),
         col("budgeted").sum().alias("budgeted"),
         col("spent").sum().alias("spent"),
+        col("upcoming").sum().alias("upcoming"),
         col("balance").sum().alias("balance"),
     ]);
 
     let result = concat([group_totals, overall_total], UnionArgs::default())
-        .context("concatenating group totals with overall total")?;
+        .shape_context("concatenating group totals with overall total")?;
 
     Ok(result)
 }
+
+/// Converts whichever [`MONEY_COLUMNS`] `frame` has from milliunits to currency units,
+/// rounded to the currency's decimal digits, for printing or writing a table. Aggregate
+/// before calling this, never after.
+pub fn to_display_units(mut frame: LazyFrame, currency: &CurrencyFormat) -> Result<LazyFrame> {
+    let schema = frame
+        .collect_schema()
+        .shape_context("reading table schema")?;
+    let conversions: Vec<Expr> = MONEY_COLUMNS
+        .iter()
+        .filter(|name| schema.contains(name))
+        .map(|name| {
+            (col(*name).cast(DataType::Float64) / lit(1000.0))
+                .round(currency.decimal_digits)
+                .alias(*name)
+        })
+        .collect();
+    Ok(frame.with_columns(conversions))
+}
+
+pub fn write_csv_string(df: &mut DataFrame) -> Result<String> {
+    let mut buf = Vec::new();
+    CsvWriter::new(&mut buf)
+        .finish(df)
+        .map_err(|err| Error::Output(format!("writing CSV: {err}")))?;
+    String::from_utf8(buf).map_err(|_| Error::Output("CSV not valid UTF-8".to_string()))
+}
@@ -0,0 +1,147 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+/// A string that must not be shown to anyone, such as the YNAB personal access token. Its
+/// `Debug` output is redacted so it cannot end up in logs or error messages by accident.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// The secret itself, for handing to the YNAB client.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// Places to read the personal access token from instead of writing it into the config.
+///
+/// When several are set they are tried in field order and the first one that yields a
+/// token wins: an unset environment variable falls through to the next source, while an
+/// unreadable file or failing command is an error. The config's own `personalAccessToken`
+/// is only used when none of these are set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenSources {
+    /// Name of an environment variable holding the token.
+    #[serde(default)]
+    pub env: Option<String>,
+    /// File holding the token. On Unix it must not be readable by group or others.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Program and arguments that print the token, e.g. `["pass", "show", "ynab"]`. Run
+    /// without a shell; its stderr goes straight to the terminal.
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+}
+
+impl TokenSources {
+    /// Finds the token, falling back to `inline` when no source is set.
+    pub fn resolve(&self, inline: Option<&Secret>) -> Result<Secret> {
+        if let Some(name) = &self.env
+            && let Ok(value) = std::env::var(name)
+        {
+            return non_empty(value, || format!("environment variable {name} is empty"));
+        }
+        if let Some(path) = &self.file {
+            return read_token_file(path);
+        }
+        if let Some(command) = &self.command {
+            return run_token_command(command);
+        }
+        if let Some(inline) = inline {
+            return Ok(inline.clone());
+        }
+        match &self.env {
+            Some(name) => Err(Error::Token(format!(
+                "environment variable {name} is not set"
+            ))),
+            None => Err(Error::ConfigInvalid(
+                "no personal access token: set personalAccessToken or personalAccessTokenFrom"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+fn non_empty(value: String, describe_empty: impl FnOnce() -> String) -> Result<Secret> {
+    let token = value.trim();
+    if token.is_empty() {
+        return Err(Error::Token(describe_empty()));
+    }
+    Ok(Secret::from(token))
+}
+
+fn read_token_file(path: &Path) -> Result<Secret> {
+    check_private(path)?;
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| Error::io(format!("reading token file {path:?}"), err))?;
+    non_empty(contents, || format!("token file {path:?} is empty"))
+}
+
+#[cfg(unix)]
+fn check_private(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = std::fs::metadata(path)
+        .map_err(|err| Error::io(format!("reading token file {path:?}"), err))?;
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(Error::Token(format!(
+            "token file {path:?} can be read by other users (mode {mode:03o}); \
+             restrict it with `chmod 600`"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn run_token_command(command: &[String]) -> Result<Secret> {
+    let [program, args @ ..] = command else {
+        return Err(Error::ConfigInvalid(
+            "personalAccessTokenFrom.command is empty".to_string(),
+        ));
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| Error::Token(format!("running `{program}`: {err}")))?;
+    if !output.status.success() {
+        return Err(Error::Token(format!(
+            "`{program}` exited with {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| Error::Token(format!("`{program}` printed something that is not UTF-8")))?;
+    non_empty(stdout, || format!("`{program}` printed nothing"))
+}
@@ -1,40 +1,15 @@
-use anyhow::{Context, Result};
 use indexmap::IndexMap;
 use polars::prelude::*;
 
-pub const CURRENCY: &str = "£";
+use crate::error::{Result, ResultExt};
+use crate::money::{CurrencyFormat, Milliunits};
 
-pub fn format_currency(value: f64, show_zero: bool) -> String {
-    let rounded = (value * 100.0).round() / 100.0;
-    if rounded == 0.0 && !show_zero {
+/// Formats `value` (in currency units) for the report, leaving zero blank unless `show_zero`.
+pub fn format_currency(value: f64, show_zero: bool, currency: &CurrencyFormat) -> String {
+    if currency.round(value) == 0.0 && !show_zero {
         return String::new();
     }
-    let sign = if rounded < 0.0 { "-" } else { "" };
-    let abs_val = rounded.abs();
-    format!("{sign}{CURRENCY}{}", format_with_commas(abs_val))
-}
-
-fn format_with_commas(value: f64) -> String {
-    let formatted = format!("{:.2}", value);
-    let (integer_part, decimal_part) = formatted.split_once('.').unwrap_or((&formatted, "00"));
-
-    let chars: Vec<char> = integer_part.chars().collect();
-    let with_commas: String = chars
-        .iter()
-        .rev()
-        .enumerate()
-        .fold(Vec::new(), |mut acc, (i, &c)| {
-            if i > 0 && i % 3 == 0 {
-                acc.push(',');
-            }
-            acc.push(c);
-            acc
-        })
-        .into_iter()
-        .rev()
-        .collect();
-
-    format!("{with_commas}.{decimal_part}")
+    currency.format(value)
 }
 
 pub fn darken_hex(color: &str, factor: f64) -> String {
@@ -53,70 +28,92 @@ pub fn darken_hex(color: &str, factor: f64) -> String {
     parse().unwrap_or_else(|| color.to_string())
 }
 
-fn with_value_columns(df: &DataFrame) -> Result<DataFrame> {
+/// Adds the yearly `planned` amount, the `remaining` balance and whether the row is planned
+/// a year at a time. Planned amounts come from `goal_per_year` where the category has a
+/// repeating target; otherwise one month's `budgeted`, times twelve for monthly categories.
+fn with_value_columns(df: &DataFrame, budgeted_months: u32) -> Result<DataFrame> {
     let is_annual = df
         .column("goal_cadence")
-        .context("goal_cadence column")?
+        .shape_context("goal_cadence column")?
         .str()
-        .context("goal_cadence as str")?
+        .shape_context("goal_cadence as str")?
         .equal("annual");
 
     let budgeted = df
         .column("budgeted")
-        .context("budgeted column")?
-        .f64()
-        .context("budgeted as f64")?;
+        .shape_context("budgeted column")?
+        .i64()
+        .shape_context("budgeted as i64")?;
+    let goals_per_year: Vec<Option<i64>> = match df.column("goal_per_year") {
+        Ok(column) => column
+            .i64()
+            .shape_context("goal_per_year as i64")?
+            .iter()
+            .collect(),
+        Err(_) => vec![None; df.height()],
+    };
+    let months = i64::from(budgeted_months.max(1));
 
-    let planned: Float64Chunked = is_annual
+    let planned: Int64Chunked = is_annual
         .iter()
         .zip(budgeted.iter())
-        .map(|(is_ann, bud)| match (is_ann, bud) {
-            (Some(true), Some(b)) => Some(b),
-            (Some(false), Some(b)) => Some(b * 12.0),
+        .zip(goals_per_year)
+        .map(|((is_ann, bud), goal)| match (is_ann, bud, goal) {
+            (_, _, Some(goal)) => Some(goal),
+            (Some(true), Some(b), None) => Some(b / months),
+            (Some(false), Some(b), None) => Some(b / months * 12),
             _ => None,
         })
         .collect();
 
-    let per_month: Float64Chunked = planned.iter().map(|p| p.map(|v| v / 12.0)).collect();
-
-    let remaining = df.column("balance").context("balance column")?.clone();
+    let remaining = df
+        .column("balance")
+        .shape_context("balance column")?
+        .clone();
 
     let is_annual_bool: BooleanChunked = is_annual;
 
     let mut result = df.clone();
     let planned_series = planned.with_name("planned".into()).into_series();
-    let per_month_series = per_month.with_name("per_month".into()).into_series();
     let is_annual_series = is_annual_bool.with_name("is_annual".into()).into_series();
 
     result
         .with_column(planned_series)
-        .context("adding planned")?;
-    result
-        .with_column(per_month_series)
-        .context("adding per_month")?;
+        .shape_context("adding planned")?;
     result
         .with_column(remaining.with_name("remaining".into()))
-        .context("adding remaining")?;
+        .shape_context("adding remaining")?;
     result
         .with_column(is_annual_series)
-        .context("adding is_annual")?;
+        .shape_context("adding is_annual")?;
 
     Ok(result)
 }
 
+fn milliunits_column(df: &DataFrame, name: &str) -> Result<Vec<Milliunits>> {
+    Ok(df
+        .column(name)
+        .shape_context(format!("{name} column"))?
+        .i64()
+        .shape_context(format!("{name} as i64"))?
+        .iter()
+        .map(|value| Milliunits(value.unwrap_or(0)))
+        .collect())
+}
+
 struct RowData {
     category: String,
-    planned: f64,
-    per_month: f64,
-    spent: f64,
-    remaining: f64,
+    planned: Milliunits,
+    spent: Milliunits,
+    upcoming: Milliunits,
+    remaining: Milliunits,
     color: String,
     is_total: bool,
     show_period_values: bool,
     is_annual: bool,
 }
 
-fn row_html(data: &RowData) -> String {
+fn row_html(data: &RowData, currency: &CurrencyFormat) -> String {
     let class_name = if data.is_total { "total" } else { "group" };
     let row_style = format!(" style=\"background-color: {};\"", data.color);
     let show_values = data.show_period_values || data.is_total;
@@ -133,7 +130,7 @@ fn row_html(data: &RowData) -> String {
     let remaining_value = if data.is_total || !show_values {
         String::new()
     } else {
-        format_currency(data.remaining, show_values)
+        format_currency(data.remaining.to_units(), show_values, currency)
     };
 
     let escaped_category = html_escape::encode_quoted_attribute(&data.category);
@@ -143,15 +140,19 @@ fn row_html(data: &RowData) -> String {
         format!("        <td>{escaped_category}</td>"),
         format!(
             r#"        <td class="number"{annual_style}>{}</td>"#,
-            format_currency(data.planned, data.is_total)
+            format_currency(data.planned.to_units(), data.is_total, currency)
         ),
         format!(
             r#"        <td class="number"{annual_style}>{}</td>"#,
-            format_currency(data.per_month, data.is_total)
+            format_currency(data.planned.to_units() / 12.0, data.is_total, currency)
+        ),
+        format!(
+            r#"        <td class="number">{}</td>"#,
+            format_currency((-data.spent).to_units(), show_values, currency)
         ),
         format!(
             r#"        <td class="number">{}</td>"#,
-            format_currency(-data.spent, show_values)
+            format_currency((-data.upcoming).to_units(), data.is_total, currency)
         ),
         format!(r#"        <td class="number">{remaining_value}</td>"#),
         "      </tr>".to_string(),
@@ -159,16 +160,205 @@ fn row_html(data: &RowData) -> String {
     .join("\n")
 }
 
+fn upcoming_section_html(upcoming_df: &DataFrame, currency: &CurrencyFormat) -> Result<String> {
+    if upcoming_df.is_empty() {
+        return Ok(String::new());
+    }
+    let dates = upcoming_df
+        .column("date")
+        .shape_context("date column")?
+        .date()
+        .shape_context("date as date")?
+        .as_date_iter();
+    let text_column = |name: &str| -> Result<Vec<String>> {
+        Ok(upcoming_df
+            .column(name)
+            .shape_context(format!("{name} column"))?
+            .str()
+            .shape_context(format!("{name} as str"))?
+            .iter()
+            .map(|value| html_escape::encode_text(value.unwrap_or("")).into_owned())
+            .collect())
+    };
+    let payees = text_column("payee_name")?;
+    let categories = text_column("category_name")?;
+    let accounts = text_column("account_name")?;
+    let amounts = milliunits_column(upcoming_df, "amount")?;
+
+    let rows: Vec<String> = dates
+        .enumerate()
+        .map(|(i, date)| {
+            let date = date.map(|d| d.format("%a %b %-d").to_string());
+            [
+                "      <tr>".to_string(),
+                format!("        <td>{}</td>", date.unwrap_or_default()),
+                format!("        <td>{}</td>", payees[i]),
+                format!("        <td>{}</td>", categories[i]),
+                format!("        <td>{}</td>", accounts[i]),
+                format!(
+                    r#"        <td class="number">{}</td>"#,
+                    format_currency((-amounts[i]).to_units(), true, currency)
+                ),
+                "      </tr>".to_string(),
+            ]
+            .join("\n")
+        })
+        .collect();
+
+    Ok([
+        "  <h2>Upcoming in period</h2>",
+        r#"  <table class="upcoming">"#,
+        "    <thead>",
+        "      <tr>",
+        "        <th>Date</th>",
+        "        <th>Payee</th>",
+        "        <th>Category</th>",
+        "        <th>Account</th>",
+        "        <th>Amount</th>",
+        "      </tr>",
+        "    </thead>",
+        "    <tbody>",
+        &rows.join("\n"),
+        "    </tbody>",
+        "  </table>",
+    ]
+    .join("\n"))
+}
+
+fn net_worth_section_html(net_worth_df: &DataFrame, currency: &CurrencyFormat) -> Result<String> {
+    let cell = |name: &str| -> Result<String> {
+        let value = milliunits_column(net_worth_df, name)?
+            .first()
+            .copied()
+            .unwrap_or(Milliunits::ZERO);
+        Ok(format!(
+            r#"        <td class="number">{}</td>"#,
+            format_currency(value.to_units(), true, currency)
+        ))
+    };
+    Ok([
+        "  <h2>Net worth</h2>".to_string(),
+        r#"  <table class="net-worth">"#.to_string(),
+        "    <thead>".to_string(),
+        "      <tr>".to_string(),
+        "        <th>Assets</th>".to_string(),
+        "        <th>Liabilities</th>".to_string(),
+        "        <th>Net worth</th>".to_string(),
+        "        <th>Change in period</th>".to_string(),
+        "      </tr>".to_string(),
+        "    </thead>".to_string(),
+        "    <tbody>".to_string(),
+        "      <tr>".to_string(),
+        cell("assets")?,
+        cell("liabilities")?,
+        cell("net_worth")?,
+        cell("net_worth_change")?,
+        "      </tr>".to_string(),
+        "    </tbody>".to_string(),
+        "  </table>".to_string(),
+    ]
+    .join("\n"))
+}
+
+fn month_summary_html(summary_df: &DataFrame, currency: &CurrencyFormat) -> Result<String> {
+    let month = summary_df
+        .column("month")
+        .shape_context("month column")?
+        .date()
+        .shape_context("month as date")?
+        .as_date_iter()
+        .next()
+        .flatten();
+    let age_of_money = summary_df
+        .column("age_of_money")
+        .shape_context("age_of_money column")?
+        .i32()
+        .shape_context("age_of_money as i32")?
+        .get(0);
+    let cell = |name: &str| -> Result<String> {
+        let value = milliunits_column(summary_df, name)?
+            .first()
+            .copied()
+            .unwrap_or(Milliunits::ZERO);
+        Ok(format!(
+            r#"        <td class="number">{}</td>"#,
+            format_currency(value.to_units(), true, currency)
+        ))
+    };
+    let heading = match month {
+        Some(month) => format!("  <h2>Budget for {}</h2>", month.format("%B %Y")),
+        None => "  <h2>Budget for the month</h2>".to_string(),
+    };
+    let age_of_money = match age_of_money {
+        Some(days) => format!("{days} days"),
+        None => String::new(),
+    };
+    Ok([
+        heading,
+        r#"  <table class="summary">"#.to_string(),
+        "    <thead>".to_string(),
+        "      <tr>".to_string(),
+        "        <th>Ready to Assign</th>".to_string(),
+        "        <th>Income</th>".to_string(),
+        "        <th>Activity</th>".to_string(),
+        "        <th>Budgeted</th>".to_string(),
+        "        <th>Age of money</th>".to_string(),
+        "      </tr>".to_string(),
+        "    </thead>".to_string(),
+        "    <tbody>".to_string(),
+        "      <tr>".to_string(),
+        cell("to_be_budgeted")?,
+        cell("income")?,
+        cell("activity")?,
+        cell("budgeted")?,
+        format!(r#"        <td class="number">{age_of_money}</td>"#),
+        "      </tr>".to_string(),
+        "    </tbody>".to_string(),
+        "  </table>".to_string(),
+    ]
+    .join("\n"))
+}
+
+/// Tables shown around the category report, each built by the matching
+/// `crate::report::build_*_table` function. Sections left as `None` are not rendered.
+#[derive(Default)]
+pub struct ReportSections {
+    /// Budget-month header above the report, from
+    /// [`crate::report::build_month_summary_table`].
+    pub month_summary: Option<LazyFrame>,
+    /// Scheduled transactions still to come, from [`crate::report::build_upcoming_table`].
+    /// Listed below the report when it has any rows.
+    pub upcoming: Option<LazyFrame>,
+    /// Net worth summary, from [`crate::report::build_net_worth_table`].
+    pub net_worth: Option<LazyFrame>,
+}
+
+/// The year the report's planned columns are for, and how many budget months the report's
+/// `budgeted` column adds up, so planned amounts can be taken from a single month.
+#[derive(Debug, Clone, Copy)]
+pub struct PlannedYear {
+    pub year: i32,
+    pub budgeted_months: u32,
+}
+
+/// Row colour for a category group that is not in `group_colors`.
+const UNWATCHED_GROUP_COLOR: &str = "#e6e6e6";
+
+/// Renders the weekly report as a standalone HTML page. Groups follow `group_colors`, then
+/// any other group in `report_table`; categories keep the table's order, as set by
+/// [`crate::report::apply_category_rules`].
 pub fn build_visual_report_html(
     report_table: LazyFrame,
+    sections: ReportSections,
     group_colors: &IndexMap<String, String>,
     week_label: &str,
-    planned_year: i32,
+    planned_year: PlannedYear,
     show_all_rows: bool,
+    currency: &CurrencyFormat,
 ) -> Result<String> {
     let report_df = report_table
         .collect()
-        .context("collecting report table for visual")?;
+        .shape_context("collecting report table for visual")?;
 
     let display_df = if show_all_rows {
         report_df.clone()
@@ -176,165 +366,173 @@ pub fn build_visual_report_html(
         report_df
             .clone()
             .lazy()
-            .filter(col("spent").neq(lit(0.0)))
+            .filter(
+                col("spent")
+                    .neq(lit(0_i64))
+                    .or(col("upcoming").neq(lit(0_i64))),
+            )
             .collect()
-            .context("filtering display rows")?
+            .shape_context("filtering display rows")?
     };
 
     let mut rows: Vec<String> = Vec::new();
-    let mut total_planned = 0.0_f64;
-    let mut total_per_month = 0.0_f64;
-    let mut total_spent = 0.0_f64;
-    let mut total_remaining = 0.0_f64;
+    let mut total_planned = Milliunits::ZERO;
+    let mut total_spent = Milliunits::ZERO;
+    let mut total_upcoming = Milliunits::ZERO;
+    let mut total_remaining = Milliunits::ZERO;
+
+    // Groups outside the watch list only have categories a rule included.
+    let mut groups: Vec<(&str, &str)> = group_colors
+        .iter()
+        .map(|(name, color)| (name.as_str(), color.as_str()))
+        .collect();
+    let report_groups = report_df
+        .column("category_group_name")
+        .shape_context("category_group_name")?
+        .str()
+        .shape_context("category_group_name str")?;
+    for name in report_groups.into_iter().flatten() {
+        if !groups.iter().any(|(group_name, _)| *group_name == name) {
+            groups.push((name, UNWATCHED_GROUP_COLOR));
+        }
+    }
 
-    for (group_name, color) in group_colors {
+    for (group_name, color) in groups {
         let group_df = report_df
             .clone()
             .lazy()
-            .filter(col("category_group_name").eq(lit(group_name.as_str())))
-            .sort(["category_name"], SortMultipleOptions::default())
+            .filter(col("category_group_name").eq(lit(group_name)))
             .collect()
-            .context("filtering group")?;
+            .shape_context("filtering group")?;
 
         let display_group_df = display_df
             .clone()
             .lazy()
-            .filter(col("category_group_name").eq(lit(group_name.as_str())))
-            .sort(["category_name"], SortMultipleOptions::default())
+            .filter(col("category_group_name").eq(lit(group_name)))
             .collect()
-            .context("filtering display group")?;
+            .shape_context("filtering display group")?;
 
         if group_df.is_empty() {
             continue;
         }
 
-        let group_values = with_value_columns(&group_df)?;
-        let display_values = with_value_columns(&display_group_df)?;
-
-        let group_planned: f64 = group_values
-            .column("planned")
-            .context("planned col")?
-            .as_materialized_series()
-            .f64()
-            .context("planned f64")?
-            .sum()
-            .unwrap_or(0.0);
-        let group_per_month: f64 = group_values
-            .column("per_month")
-            .context("per_month col")?
-            .as_materialized_series()
-            .f64()
-            .context("per_month f64")?
-            .sum()
-            .unwrap_or(0.0);
-        let group_spent: f64 = group_values
-            .column("spent")
-            .context("spent col")?
-            .as_materialized_series()
-            .f64()
-            .context("spent f64")?
-            .sum()
-            .unwrap_or(0.0);
-        let group_remaining: f64 = group_values
-            .column("remaining")
-            .context("remaining col")?
-            .as_materialized_series()
-            .f64()
-            .context("remaining f64")?
-            .sum()
-            .unwrap_or(0.0);
+        let group_values = with_value_columns(&group_df, planned_year.budgeted_months)?;
+        let display_values = with_value_columns(&display_group_df, planned_year.budgeted_months)?;
+
+        let group_planned: Milliunits = milliunits_column(&group_values, "planned")?
+            .into_iter()
+            .sum();
+        let group_spent: Milliunits = milliunits_column(&group_values, "spent")?.into_iter().sum();
+        let group_upcoming: Milliunits = milliunits_column(&group_values, "upcoming")?
+            .into_iter()
+            .sum();
+        let group_remaining: Milliunits = milliunits_column(&group_values, "remaining")?
+            .into_iter()
+            .sum();
 
         total_planned += group_planned;
-        total_per_month += group_per_month;
         total_spent += group_spent;
+        total_upcoming += group_upcoming;
         total_remaining += group_remaining;
 
+        let planned = milliunits_column(&display_values, "planned")?;
+        let spent = milliunits_column(&display_values, "spent")?;
+        let upcoming = milliunits_column(&display_values, "upcoming")?;
+        let remaining = milliunits_column(&display_values, "remaining")?;
         for i in 0..display_values.height() {
             let cat_name = display_values
                 .column("category_name")
-                .context("cat name")?
+                .shape_context("cat name")?
                 .str()
-                .context("cat name str")?
+                .shape_context("cat name str")?
                 .get(i)
                 .unwrap_or("");
-            let planned: f64 = display_values
-                .column("planned")
-                .context("planned")?
-                .f64()
-                .context("planned f64")?
-                .get(i)
-                .unwrap_or(0.0);
-            let per_month: f64 = display_values
-                .column("per_month")
-                .context("per_month")?
-                .f64()
-                .context("per_month f64")?
-                .get(i)
-                .unwrap_or(0.0);
-            let spent: f64 = display_values
-                .column("spent")
-                .context("spent")?
-                .f64()
-                .context("spent f64")?
-                .get(i)
-                .unwrap_or(0.0);
-            let remaining: f64 = display_values
-                .column("remaining")
-                .context("remaining")?
-                .f64()
-                .context("remaining f64")?
-                .get(i)
-                .unwrap_or(0.0);
             let is_annual = display_values
                 .column("is_annual")
-                .context("is_annual")?
+                .shape_context("is_annual")?
                 .bool()
-                .context("is_annual bool")?
+                .shape_context("is_annual bool")?
                 .get(i)
                 .unwrap_or(false);
 
-            rows.push(row_html(&RowData {
-                category: cat_name.to_string(),
-                planned,
-                per_month,
-                spent,
-                remaining,
-                color: color.clone(),
-                is_total: false,
-                show_period_values: spent != 0.0,
-                is_annual,
-            }));
+            rows.push(row_html(
+                &RowData {
+                    category: cat_name.to_string(),
+                    planned: planned[i],
+                    spent: spent[i],
+                    upcoming: upcoming[i],
+                    remaining: remaining[i],
+                    color: color.to_string(),
+                    is_total: false,
+                    show_period_values: spent[i] != Milliunits::ZERO
+                        || upcoming[i] != Milliunits::ZERO,
+                    is_annual,
+                },
+                currency,
+            ));
         }
 
-        rows.push(row_html(&RowData {
-            category: format!("Total {group_name}"),
-            planned: group_planned,
-            per_month: group_per_month,
-            spent: group_spent,
-            remaining: group_remaining,
-            color: darken_hex(color, 0.85),
-            is_total: true,
-            show_period_values: true,
-            is_annual: false,
-        }));
+        rows.push(row_html(
+            &RowData {
+                category: format!("Total {group_name}"),
+                planned: group_planned,
+                spent: group_spent,
+                upcoming: group_upcoming,
+                remaining: group_remaining,
+                color: darken_hex(color, 0.85),
+                is_total: true,
+                show_period_values: true,
+                is_annual: false,
+            },
+            currency,
+        ));
     }
 
     if !rows.is_empty() {
-        rows.push(row_html(&RowData {
-            category: "Total".to_string(),
-            planned: total_planned,
-            per_month: total_per_month,
-            spent: total_spent,
-            remaining: total_remaining,
-            color: "#b7b7b7".to_string(),
-            is_total: true,
-            show_period_values: true,
-            is_annual: false,
-        }));
+        rows.push(row_html(
+            &RowData {
+                category: "Total".to_string(),
+                planned: total_planned,
+                spent: total_spent,
+                upcoming: total_upcoming,
+                remaining: total_remaining,
+                color: "#b7b7b7".to_string(),
+                is_total: true,
+                show_period_values: true,
+                is_annual: false,
+            },
+            currency,
+        ));
     }
 
     let body_rows = rows.join("\n");
     let escaped_week = html_escape::encode_text(week_label);
+    let mut table_start = vec![format!("  <h1>{escaped_week}</h1>")];
+    if let Some(month_summary) = sections.month_summary {
+        let summary_df = month_summary
+            .collect()
+            .shape_context("collecting month summary for visual")?;
+        table_start.push(month_summary_html(&summary_df, currency)?);
+    }
+    let table_start = table_start.join("\n");
+
+    let mut table_end = vec!["  </table>".to_string()];
+    if let Some(upcoming) = sections.upcoming {
+        let upcoming_df = upcoming
+            .collect()
+            .shape_context("collecting upcoming transactions for visual")?;
+        let upcoming_section = upcoming_section_html(&upcoming_df, currency)?;
+        if !upcoming_section.is_empty() {
+            table_end.push(upcoming_section);
+        }
+    }
+    if let Some(net_worth) = sections.net_worth {
+        let net_worth_df = net_worth
+            .collect()
+            .shape_context("collecting net worth for visual")?;
+        table_end.push(net_worth_section_html(&net_worth_df, currency)?);
+    }
+    let table_end = table_end.join("\n");
 
     let html = [
         "<!DOCTYPE html>",
@@ -363,6 +561,15 @@ pub fn build_visual_report_html(
         "      letter-spacing: 0.02em;",
         "      text-transform: uppercase;",
         "    }",
+        "    h2 {",
+        "      font-size: 16px;",
+        "      margin: 24px 0 12px 0;",
+        "      letter-spacing: 0.02em;",
+        "      text-transform: uppercase;",
+        "    }",
+        "    table.summary {",
+        "      margin-bottom: 16px;",
+        "    }",
         "    table {",
         "      width: 100%;",
         "      border-collapse: collapse;",
@@ -403,24 +610,31 @@ pub fn build_visual_report_html(
         "  </style>",
         "</head>",
         "<body>",
-        &format!("  <h1>{escaped_week}</h1>"),
+        &table_start,
         r#"  <table class="selectable">"#,
         "    <thead>",
         "      <tr>",
         r#"        <th rowspan="2">Category</th>"#,
-        &format!(r#"        <th rowspan="2">{planned_year} (planned)</th>"#),
-        &format!(r#"        <th rowspan="2">{planned_year} per month</th>"#),
-        &format!(r#"        <th colspan="2">{escaped_week}</th>"#),
+        &format!(
+            r#"        <th rowspan="2">{} (planned)</th>"#,
+            planned_year.year
+        ),
+        &format!(
+            r#"        <th rowspan="2">{} per month</th>"#,
+            planned_year.year
+        ),
+        &format!(r#"        <th colspan="3">{escaped_week}</th>"#),
         "      </tr>",
         "      <tr>",
         "        <th>Spent</th>",
+        "        <th>Upcoming</th>",
         "        <th>Remaining in period</th>",
         "      </tr>",
         "    </thead>",
         "    <tbody>",
         &body_rows,
         "    </tbody>",
-        "  </table>",
+        &table_end,
         "  <script>",
         r#"    const table = document.querySelector("table.selectable");"#,
         "    if (table) {",
@@ -0,0 +1,112 @@
+use indexmap::IndexMap;
+use regex::Regex;
+
+use crate::error::{Error, Result};
+use crate::ynab::CategoryGroup;
+
+/// How many "did you mean" suggestions to offer for a watch list key that matches nothing.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// What one `categoryGroupWatchList` or `categoryRules` key matches:
+///
+/// - `id:<group id>` matches the group with that YNAB id, whatever it is called now.
+/// - `glob:<pattern>` matches group names with a glob such as `Bills*`, ignoring case.
+/// - `re:<regex>` matches group names with a regular expression, anywhere in the name.
+/// - Anything else is a group name, compared ignoring case, emoji and extra spaces, so
+///   `essentials` matches `🏠 Essentials`.
+#[derive(Debug, Clone)]
+pub enum GroupPattern {
+    Id(String),
+    Glob(glob::Pattern),
+    Regex(Regex),
+    Name(String),
+}
+
+impl GroupPattern {
+    pub fn parse(key: &str) -> Result<Self> {
+        if let Some(id) = key.strip_prefix("id:") {
+            Ok(Self::Id(id.trim().to_string()))
+        } else if let Some(pattern) = key.strip_prefix("glob:") {
+            glob::Pattern::new(pattern)
+                .map(Self::Glob)
+                .map_err(|e| Error::ConfigInvalid(format!("invalid glob: {e}")))
+        } else if let Some(pattern) = key.strip_prefix("re:") {
+            Regex::new(pattern)
+                .map(Self::Regex)
+                .map_err(|e| Error::ConfigInvalid(format!("invalid regex: {e}")))
+        } else {
+            Ok(Self::Name(normalize_name(key)))
+        }
+    }
+
+    pub fn matches(&self, group: &CategoryGroup) -> bool {
+        match self {
+            Self::Id(id) => &group.id == id,
+            Self::Glob(pattern) => pattern.matches_with(
+                &group.name,
+                glob::MatchOptions {
+                    case_sensitive: false,
+                    ..glob::MatchOptions::new()
+                },
+            ),
+            Self::Regex(regex) => regex.is_match(&group.name),
+            Self::Name(name) => &normalize_name(&group.name) == name,
+        }
+    }
+}
+
+/// `name` lowercased, with emoji and other symbols dropped and runs of spaces collapsed.
+pub fn normalize_name(name: &str) -> String {
+    let kept: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation())
+        .flat_map(char::to_lowercase)
+        .collect();
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The watch list, or category rules, with their keys replaced by the names of the groups
+/// they match, in key order. A group matched by several keys takes the value of the first.
+pub fn resolve<V: Clone>(
+    groups: &[CategoryGroup],
+    watch_list: &IndexMap<String, V>,
+) -> Result<IndexMap<String, V>> {
+    let mut resolved = IndexMap::new();
+    for (key, value) in watch_list {
+        let pattern = GroupPattern::parse(key)?;
+        for group in groups.iter().filter(|g| pattern.matches(g)) {
+            resolved
+                .entry(group.name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Group names close to `key`, best first, for telling someone what a watch list key that
+/// matches nothing was probably meant to be. Only plain names get suggestions.
+pub fn suggestions(groups: &[CategoryGroup], key: &str) -> Vec<String> {
+    let Ok(GroupPattern::Name(wanted)) = GroupPattern::parse(key) else {
+        return vec![];
+    };
+    let allowed_edits = (wanted.chars().count() / 3).max(1);
+    let mut scored: Vec<(usize, &str)> = groups
+        .iter()
+        .filter_map(|g| {
+            let name = normalize_name(&g.name);
+            let contained = name.contains(&wanted) || wanted.contains(&name);
+            let edits = if contained && !wanted.is_empty() && !name.is_empty() {
+                0
+            } else {
+                strsim::damerau_levenshtein(&wanted, &name)
+            };
+            (edits <= allowed_edits).then_some((edits, g.name.as_str()))
+        })
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
@@ -1,37 +1,94 @@
-use anyhow::{Context, Result};
-use chrono::{Datelike, NaiveDate};
-use futures::executor::block_on;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use futures::{StreamExt, TryStreamExt, stream};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
-use ynab_api::apis::configuration::{ApiKey, Configuration};
-use ynab_api::apis::{budgets_api, categories_api, transactions_api};
+use ynab_api::apis::configuration::Configuration;
+use ynab_api::apis::urlencode;
+
+use crate::error::{Error, Result, ResultExt};
+use crate::money::{CurrencyFormat, Milliunits};
 
 // --- API response types ---
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BudgetSummary {
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub currency_format: Option<CurrencyFormat>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Category {
     pub id: String,
     pub name: String,
     #[serde(default)]
     pub category_group_name: Option<String>,
     #[serde(default)]
-    pub budgeted: i64,
+    pub budgeted: Milliunits,
     #[serde(default)]
-    pub balance: i64,
+    pub balance: Milliunits,
+    /// `TB`, `TBD`, `MF`, `NEED` or `DEBT`, or `None` when the category has no target.
+    #[serde(default)]
+    pub goal_type: Option<String>,
     #[serde(default)]
     pub goal_cadence: Option<i32>,
+    /// How many cadence units between repeats, e.g. 2 with a weekly cadence is fortnightly.
+    #[serde(default)]
+    pub goal_cadence_frequency: Option<i32>,
+    #[serde(default)]
+    pub goal_target: Option<Milliunits>,
+    #[serde(default)]
+    pub goal_target_month: Option<NaiveDate>,
+    #[serde(default)]
+    pub goal_percentage_complete: Option<i32>,
+    #[serde(default)]
+    pub goal_under_funded: Option<Milliunits>,
+    #[serde(default)]
+    pub goal_overall_left: Option<Milliunits>,
     #[serde(default)]
-    pub goal_target: Option<i64>,
+    pub goal_months_to_budget: Option<i32>,
     #[serde(default)]
     pub hidden: bool,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+impl Category {
+    /// What the category's repeating target asks for over a year, from `goal_target`,
+    /// `goal_cadence` and `goal_cadence_frequency`. YNAB's cadences 1, 2 and 13 repeat every
+    /// `goal_cadence_frequency` months, weeks or years; 3 to 12 repeat every 2 to 11 months
+    /// and 14 every two years. `None` when the target does not repeat.
+    pub fn goal_per_year(&self) -> Option<Milliunits> {
+        let target = self.goal_target?.0;
+        let every = i64::from(self.goal_cadence_frequency.unwrap_or(1).max(1));
+        let per_year = match self.goal_cadence? {
+            1 => target * 12 / every,
+            2 => target * 52 / every,
+            13 => target / every,
+            cadence @ 3..=12 => target * 12 / i64::from(cadence - 1),
+            14 => target / 2,
+            _ => return None,
+        };
+        Some(Milliunits(per_year))
+    }
+
+    /// Whether the category's target repeats at least once a month.
+    pub fn has_monthly_goal(&self) -> bool {
+        let every = self.goal_cadence_frequency.unwrap_or(1);
+        self.goal_target.is_some()
+            && match self.goal_cadence {
+                Some(1) => every <= 1,
+                Some(2) => every <= 4,
+                _ => false,
+            }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CategoryGroup {
     pub id: String,
     pub name: String,
@@ -43,28 +100,368 @@ pub struct CategoryGroup {
     pub categories: Vec<Category>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// The kind of account, as chosen when it was added to YNAB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountType {
+    Checking,
+    Savings,
+    Cash,
+    CreditCard,
+    LineOfCredit,
+    OtherAsset,
+    OtherLiability,
+    Mortgage,
+    AutoLoan,
+    StudentLoan,
+    PersonalLoan,
+    MedicalDebt,
+    OtherDebt,
+    #[serde(other)]
+    Other,
+}
+
+impl AccountType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Checking => "checking",
+            Self::Savings => "savings",
+            Self::Cash => "cash",
+            Self::CreditCard => "creditCard",
+            Self::LineOfCredit => "lineOfCredit",
+            Self::OtherAsset => "otherAsset",
+            Self::OtherLiability => "otherLiability",
+            Self::Mortgage => "mortgage",
+            Self::AutoLoan => "autoLoan",
+            Self::StudentLoan => "studentLoan",
+            Self::PersonalLoan => "personalLoan",
+            Self::MedicalDebt => "medicalDebt",
+            Self::OtherDebt => "otherDebt",
+            Self::Other => "other",
+        }
+    }
+
+    /// Credit cards, loans and other debts; everything else counts as an asset.
+    pub fn is_liability(self) -> bool {
+        matches!(
+            self,
+            Self::CreditCard
+                | Self::LineOfCredit
+                | Self::OtherLiability
+                | Self::Mortgage
+                | Self::AutoLoan
+                | Self::StudentLoan
+                | Self::PersonalLoan
+                | Self::MedicalDebt
+                | Self::OtherDebt
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    /// Whether the account is part of the budget, as opposed to a tracking account.
+    #[serde(default)]
+    pub on_budget: bool,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub balance: Milliunits,
+    #[serde(default)]
+    pub cleared_balance: Milliunits,
+    #[serde(default)]
+    pub uncleared_balance: Milliunits,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Whether a transaction has cleared the bank, as marked in YNAB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClearedStatus {
+    #[default]
+    Uncleared,
+    Cleared,
+    Reconciled,
+}
+
+impl ClearedStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Uncleared => "uncleared",
+            Self::Cleared => "cleared",
+            Self::Reconciled => "reconciled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubTransaction {
     #[serde(default)]
-    pub amount: i64,
+    pub amount: Milliunits,
+    #[serde(default)]
+    pub memo: Option<String>,
     #[serde(default)]
     pub payee_name: Option<String>,
     #[serde(default)]
     pub category_name: Option<String>,
+    #[serde(default)]
+    pub transfer_account_id: Option<String>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// One category line of a split scheduled transaction. Unlike [`SubTransaction`], YNAB only
+/// gives the id of its category, not the name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledSubTransaction {
+    #[serde(default)]
+    pub amount: Milliunits,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub transfer_account_id: Option<String>,
+    #[serde(default)]
+    pub deleted: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: String,
     pub date: NaiveDate,
     #[serde(default)]
-    pub amount: i64,
+    pub amount: Milliunits,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub cleared: ClearedStatus,
+    #[serde(default)]
+    pub approved: bool,
+    #[serde(default)]
+    pub flag_color: Option<String>,
+    #[serde(default)]
+    pub account_id: String,
+    #[serde(default)]
+    pub account_name: String,
     #[serde(default)]
     pub payee_name: Option<String>,
     #[serde(default)]
     pub category_name: Option<String>,
     #[serde(default)]
+    pub transfer_account_id: Option<String>,
+    #[serde(default)]
+    pub import_id: Option<String>,
+    #[serde(default)]
     pub subtransactions: Vec<SubTransaction>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Budget-wide totals for one month.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthSummary {
+    pub month: NaiveDate,
+    #[serde(default)]
+    pub income: Milliunits,
+    #[serde(default)]
+    pub budgeted: Milliunits,
+    #[serde(default)]
+    pub activity: Milliunits,
+    /// Ready to Assign: income not yet given to a category.
+    #[serde(default)]
+    pub to_be_budgeted: Milliunits,
+    /// Days between earning money and spending it, when YNAB can tell.
+    #[serde(default)]
+    pub age_of_money: Option<i32>,
+}
+
+/// One budget month: its budget-wide totals and every category's values for the month.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthDetail {
+    #[serde(flatten)]
+    pub summary: MonthSummary,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+}
+
+/// What a report on one budget fetches up front, see [`YnabApi::get_budget_data`].
+#[derive(Debug, Clone)]
+pub struct BudgetData {
+    pub category_groups: Vec<CategoryGroup>,
+    pub month: MonthDetail,
+    pub transactions: Vec<Transaction>,
+    pub scheduled_transactions: Vec<ScheduledTransaction>,
+    /// Only fetched when asked for.
+    pub accounts: Option<Vec<Account>>,
+}
+
+/// How often a scheduled transaction repeats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Frequency {
+    #[default]
+    Never,
+    Daily,
+    Weekly,
+    EveryOtherWeek,
+    TwiceAMonth,
+    Every4Weeks,
+    Monthly,
+    EveryOtherMonth,
+    Every3Months,
+    Every4Months,
+    TwiceAYear,
+    Yearly,
+    EveryOtherYear,
+}
+
+impl Frequency {
+    /// The `n`th occurrence of a schedule that started on `first`, or `None` past the last
+    /// representable date. Month-based schedules keep `first`'s day of the month, clamped to
+    /// the end of shorter months; twice a month repeats fifteen days after each monthly date.
+    pub fn nth_date(self, first: NaiveDate, n: u32) -> Option<NaiveDate> {
+        let add_days =
+            |days: u32| first.checked_add_days(Days::new(u64::from(n) * u64::from(days)));
+        let add_months =
+            |months: u32| first.checked_add_months(Months::new(n.checked_mul(months)?));
+        match self {
+            Self::Never => (n == 0).then_some(first),
+            Self::Daily => add_days(1),
+            Self::Weekly => add_days(7),
+            Self::EveryOtherWeek => add_days(14),
+            Self::Every4Weeks => add_days(28),
+            Self::TwiceAMonth => {
+                let monthly = first.checked_add_months(Months::new(n / 2))?;
+                if n.is_multiple_of(2) {
+                    Some(monthly)
+                } else {
+                    monthly.checked_add_days(Days::new(15))
+                }
+            }
+            Self::Monthly => add_months(1),
+            Self::EveryOtherMonth => add_months(2),
+            Self::Every3Months => add_months(3),
+            Self::Every4Months => add_months(4),
+            Self::TwiceAYear => add_months(6),
+            Self::Yearly => add_months(12),
+            Self::EveryOtherYear => add_months(24),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTransaction {
+    pub id: String,
+    pub date_first: NaiveDate,
+    /// The next occurrence that has not been entered yet.
+    pub date_next: NaiveDate,
+    #[serde(default)]
+    pub frequency: Frequency,
+    #[serde(default)]
+    pub amount: Milliunits,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub flag_color: Option<String>,
+    #[serde(default)]
+    pub account_id: String,
+    #[serde(default)]
+    pub account_name: String,
+    #[serde(default)]
+    pub payee_name: Option<String>,
+    #[serde(default)]
+    pub category_name: Option<String>,
+    #[serde(default)]
+    pub transfer_account_id: Option<String>,
+    #[serde(default)]
+    pub subtransactions: Vec<ScheduledSubTransaction>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Category names keyed by category id, for resolving the categories of split scheduled
+/// transactions.
+pub fn category_names_by_id(groups: &[CategoryGroup]) -> HashMap<&str, &str> {
+    groups
+        .iter()
+        .flat_map(|g| &g.categories)
+        .map(|c| (c.id.as_str(), c.name.as_str()))
+        .collect()
+}
+
+impl ScheduledTransaction {
+    /// Dates from `start` to `end` inclusive on which this schedule still has to be entered,
+    /// i.e. `date_next` and the occurrences after it. When `date_next` has been moved off
+    /// the cadence started by `date_first`, later occurrences repeat from `date_next`.
+    pub fn occurrences(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        let from = start.max(self.date_next);
+        let on_cadence = (0..)
+            .map_while(|n| self.frequency.nth_date(self.date_first, n))
+            .take_while(|date| *date <= self.date_next)
+            .any(|date| date == self.date_next);
+        let anchor = if on_cadence {
+            self.date_first
+        } else {
+            self.date_next
+        };
+        (0..)
+            .map_while(|n| self.frequency.nth_date(anchor, n))
+            .take_while(|date| *date <= end)
+            .filter(|date| *date >= from)
+            .collect()
+    }
+
+    /// The transaction this schedule will enter on `date`, not yet cleared or approved. The
+    /// categories of split lines are looked up in `category_names`, see
+    /// [`category_names_by_id`].
+    pub fn on(&self, date: NaiveDate, category_names: &HashMap<&str, &str>) -> Transaction {
+        Transaction {
+            id: format!("{}_{date}", self.id),
+            date,
+            amount: self.amount,
+            memo: self.memo.clone(),
+            cleared: ClearedStatus::Uncleared,
+            approved: false,
+            flag_color: self.flag_color.clone(),
+            account_id: self.account_id.clone(),
+            account_name: self.account_name.clone(),
+            payee_name: self.payee_name.clone(),
+            category_name: self.category_name.clone(),
+            transfer_account_id: self.transfer_account_id.clone(),
+            import_id: None,
+            subtransactions: self
+                .subtransactions
+                .iter()
+                .map(|sub| SubTransaction {
+                    amount: sub.amount,
+                    memo: sub.memo.clone(),
+                    payee_name: None,
+                    category_name: sub
+                        .category_id
+                        .as_deref()
+                        .and_then(|id| category_names.get(id))
+                        .map(|name| name.to_string()),
+                    transfer_account_id: sub.transfer_account_id.clone(),
+                    deleted: sub.deleted,
+                })
+                .collect(),
+            deleted: self.deleted,
+        }
+    }
+}
+
+/// A page of results from a YNAB delta request, along with the server knowledge
+/// to pass as `last_knowledge_of_server` on the next request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delta<T> {
+    pub items: Vec<T>,
+    pub server_knowledge: i64,
 }
 
 // --- API response envelopes ---
@@ -82,6 +479,7 @@ struct BudgetsResponse {
 #[derive(Debug, Deserialize)]
 struct CategoriesResponseData {
     category_groups: Vec<CategoryGroup>,
+    server_knowledge: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -99,9 +497,20 @@ struct CategoryResponse {
     data: CategoryResponseData,
 }
 
+#[derive(Debug, Deserialize)]
+struct MonthResponseData {
+    month: MonthDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct MonthResponse {
+    data: MonthResponseData,
+}
+
 #[derive(Debug, Deserialize)]
 struct TransactionsResponseData {
     transactions: Vec<Transaction>,
+    server_knowledge: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -109,66 +518,654 @@ struct TransactionsResponse {
     data: TransactionsResponseData,
 }
 
+#[derive(Debug, Deserialize)]
+struct AccountsResponseData {
+    accounts: Vec<Account>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountsResponse {
+    data: AccountsResponseData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledTransactionsResponseData {
+    scheduled_transactions: Vec<ScheduledTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledTransactionsResponse {
+    data: ScheduledTransactionsResponseData,
+}
+
 // --- Client trait ---
 
 pub trait YnabApi {
     fn get_budgets(&self) -> Result<Vec<BudgetSummary>>;
     fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>>;
+    /// Every account in the budget, including closed ones.
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>>;
     fn get_month_category(
         &self,
         budget_id: &str,
         month: NaiveDate,
         category_id: &str,
     ) -> Result<Category>;
+    /// Budget-wide totals and every category's values for the month containing `month`, in
+    /// a single request.
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail>;
+    /// [`YnabApi::get_month_detail`] for several months, in the order given. The default
+    /// fetches them one at a time; clients that can issue requests concurrently override it.
+    fn get_month_details(&self, budget_id: &str, months: &[NaiveDate]) -> Result<Vec<MonthDetail>> {
+        months
+            .iter()
+            .map(|month| self.get_month_detail(budget_id, *month))
+            .collect()
+    }
+    /// Month values for several categories, in the order given. The default fetches them one
+    /// at a time; clients that can issue requests concurrently override it.
+    fn get_month_categories_by_id(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_ids: &[&str],
+    ) -> Result<Vec<Category>> {
+        category_ids
+            .iter()
+            .map(|id| self.get_month_category(budget_id, month, id))
+            .collect()
+    }
     fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>>;
+    /// Every live scheduled transaction, with `date_next` set to its next unentered date.
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>>;
+    /// The category groups, the budget month containing `month`, the transactions since
+    /// `since_date`, the scheduled transactions and, `with_accounts`, the accounts of one
+    /// budget. The default fetches them one after another; clients that can issue requests
+    /// concurrently override it.
+    fn get_budget_data(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        since_date: NaiveDate,
+        with_accounts: bool,
+    ) -> Result<BudgetData> {
+        Ok(BudgetData {
+            category_groups: self.get_category_groups(budget_id)?,
+            month: self.get_month_detail(budget_id, month)?,
+            transactions: self.get_transactions(budget_id, since_date)?,
+            accounts: with_accounts
+                .then(|| self.get_accounts(budget_id))
+                .transpose()?,
+            scheduled_transactions: self.get_scheduled_transactions(budget_id)?,
+        })
+    }
+
+    /// Category groups changed since `last_knowledge_of_server`, or all of them when `None`.
+    /// Deleted groups and categories are returned with `deleted: true`.
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>>;
+
+    /// Transactions on or after `since_date` changed since `last_knowledge_of_server`, or all
+    /// of them when `None`. Deleted transactions are returned with `deleted: true`.
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>>;
+}
+
+impl<T: YnabApi + ?Sized> YnabApi for &T {
+    fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        (**self).get_budgets()
+    }
+
+    fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
+        (**self).get_category_groups(budget_id)
+    }
+
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        (**self).get_accounts(budget_id)
+    }
+
+    fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        (**self).get_month_category(budget_id, month, category_id)
+    }
+
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        (**self).get_month_detail(budget_id, month)
+    }
+
+    fn get_month_details(&self, budget_id: &str, months: &[NaiveDate]) -> Result<Vec<MonthDetail>> {
+        (**self).get_month_details(budget_id, months)
+    }
+
+    fn get_month_categories_by_id(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_ids: &[&str],
+    ) -> Result<Vec<Category>> {
+        (**self).get_month_categories_by_id(budget_id, month, category_ids)
+    }
+
+    fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
+        (**self).get_transactions(budget_id, since_date)
+    }
+
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        (**self).get_scheduled_transactions(budget_id)
+    }
+
+    fn get_budget_data(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        since_date: NaiveDate,
+        with_accounts: bool,
+    ) -> Result<BudgetData> {
+        (**self).get_budget_data(budget_id, month, since_date, with_accounts)
+    }
+
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        (**self).get_category_groups_delta(budget_id, last_knowledge_of_server)
+    }
+
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        (**self).get_transactions_delta(budget_id, since_date, last_knowledge_of_server)
+    }
+}
+
+/// The month values of `categories`: taken from `month` for those it lists, and fetched with
+/// one request per category for any it does not. Categories keep their group name from
+/// `categories` when the month listing omits it.
+pub fn get_month_categories_for(
+    api: &dyn YnabApi,
+    budget_id: &str,
+    month: &MonthDetail,
+    categories: &[Category],
+) -> Result<Vec<Category>> {
+    let listed: HashMap<&str, &Category> = month
+        .categories
+        .iter()
+        .map(|c| (c.id.as_str(), c))
+        .collect();
+
+    let unlisted_ids: Vec<&str> = categories
+        .iter()
+        .map(|c| c.id.as_str())
+        .filter(|id| !listed.contains_key(id))
+        .collect();
+    let fetched = api.get_month_categories_by_id(budget_id, month.summary.month, &unlisted_ids)?;
+    let mut fetched: HashMap<String, Category> =
+        fetched.into_iter().map(|c| (c.id.clone(), c)).collect();
+
+    categories
+        .iter()
+        .map(|cat| {
+            let month_category = match listed.get(cat.id.as_str()) {
+                Some(found) => (*found).clone(),
+                None => fetched.remove(&cat.id).ok_or_else(|| {
+                    Error::DataShape(format!("no month values returned for category {}", cat.id))
+                })?,
+            };
+            Ok(Category {
+                category_group_name: month_category
+                    .category_group_name
+                    .or_else(|| cat.category_group_name.clone()),
+                ..month_category
+            })
+        })
+        .collect()
+}
+
+// --- Async client trait ---
+
+/// Async counterpart of [`YnabApi`], for callers that drive their own runtime and want to
+/// issue independent requests concurrently.
+pub trait AsyncYnabApi {
+    fn get_budgets(&self) -> impl Future<Output = Result<Vec<BudgetSummary>>> + Send;
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> impl Future<Output = Result<Delta<CategoryGroup>>> + Send;
+    fn get_accounts(&self, budget_id: &str) -> impl Future<Output = Result<Vec<Account>>> + Send;
+    fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> impl Future<Output = Result<Category>> + Send;
+    fn get_month_detail(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+    ) -> impl Future<Output = Result<MonthDetail>> + Send;
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> impl Future<Output = Result<Delta<Transaction>>> + Send;
+    fn get_scheduled_transactions(
+        &self,
+        budget_id: &str,
+    ) -> impl Future<Output = Result<Vec<ScheduledTransaction>>> + Send;
 }
 
 // --- HTTP implementation ---
 
+/// YNAB addresses budget months by their first day.
+fn month_param(month: NaiveDate) -> Result<String> {
+    let first_of_month = NaiveDate::from_ymd_opt(month.year(), month.month(), 1)
+        .ok_or_else(|| Error::DataShape(format!("invalid month from {month}")))?;
+    Ok(first_of_month.format("%Y-%m-%d").to_string())
+}
+
+/// How many requests [`HttpYnabClient`] keeps in flight at once unless told otherwise.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Request usage reported by YNAB in the `X-Rate-Limit` header, e.g. `36/200`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub used: u32,
+    pub limit: u32,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let value = headers.get("x-rate-limit")?.to_str().ok()?;
+        let (used, limit) = value.trim().split_once('/')?;
+        Some(Self {
+            used: used.trim().parse().ok()?,
+            limit: limit.trim().parse().ok()?,
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.used >= self.limit
+    }
+}
+
+/// Carried by [`Error::RateLimited`] when YNAB refuses requests because the hourly quota is
+/// used up, or keeps answering 429 after every retry.
+#[derive(Debug, Clone)]
+pub struct RateLimitExceeded {
+    pub rate_limit: Option<RateLimit>,
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "YNAB rate limit exceeded")?;
+        if let Some(rate_limit) = self.rate_limit {
+            write!(
+                f,
+                " ({} of {} requests used in the last hour)",
+                rate_limit.used, rate_limit.limit
+            )?;
+        }
+        match self.retry_after {
+            Some(wait) => write!(f, "; try again in {} seconds", wait.as_secs().max(1)),
+            None => write!(f, "; try again later"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+/// How [`HttpYnabClient`] retries rate-limited (429) and transient server errors (500, 502,
+/// 503, 504) as well as connection failures. Waits double from `initial_backoff` up to
+/// `max_backoff`, with random jitter, unless the server sends a `Retry-After`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Half the exponential step plus a random share of the other half, so concurrent
+    /// requests retrying at once spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let step = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        let half = step / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 500 | 502 | 503 | 504)
+}
+
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let seconds: u64 = headers
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// The `detail` of a YNAB error body, or the raw body when it is not one.
+fn error_detail(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value["error"]["detail"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+fn status_error(what: &str, status: reqwest::StatusCode, body: &str) -> Error {
+    let detail = error_detail(body);
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED => Error::Auth(detail),
+        reqwest::StatusCode::NOT_FOUND => Error::NotFound(format!("resource for {what}")),
+        _ => Error::Api {
+            what: what.to_string(),
+            detail: format!("status {status}: {detail}"),
+        },
+    }
+}
+
+/// Async-first YNAB client. Use it through [`AsyncYnabApi`] from async code, or through the
+/// blocking [`YnabApi`] facade, which drives requests on a runtime owned by the client and
+/// issues batched requests concurrently.
 pub struct HttpYnabClient {
     configuration: Configuration,
+    runtime: tokio::runtime::Runtime,
+    max_concurrent_requests: usize,
+    /// One permit per request allowed in flight, shared by everything the client sends.
+    request_slots: tokio::sync::Semaphore,
+    retry_policy: RetryPolicy,
+    rate_limit: Mutex<Option<RateLimit>>,
 }
 
 impl HttpYnabClient {
     pub fn new(token: &str) -> Result<Self> {
         let mut configuration = Configuration::new();
-        configuration.api_key = Some(ApiKey {
-            prefix: Some("Bearer".to_string()),
-            key: token.to_string(),
-        });
+        configuration.bearer_access_token = Some(token.to_string());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| Error::io("starting async runtime for YNAB client", err))?;
+
+        Ok(Self {
+            configuration,
+            runtime,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            request_slots: tokio::sync::Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            retry_policy: RetryPolicy::default(),
+            rate_limit: Mutex::new(None),
+        })
+    }
 
-        Ok(Self { configuration })
+    /// Points the client at a different server, e.g. a local stand-in for tests.
+    pub fn with_base_path(mut self, base_path: impl Into<String>) -> Self {
+        self.configuration.base_path = base_path.into();
+        self
     }
 
-    fn map_model<TSrc, TDst>(&self, src: TSrc, name: &str) -> Result<TDst>
-    where
-        TSrc: Serialize,
-        TDst: DeserializeOwned,
-    {
-        let value = serde_json::to_value(src)
-            .with_context(|| format!("serializing YNAB response model {name}"))?;
-        serde_json::from_value(value)
-            .with_context(|| format!("deserializing YNAB response model into crustynab {name}"))
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self.request_slots = tokio::sync::Semaphore::new(self.max_concurrent_requests);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Usage from the most recent `X-Rate-Limit` header, if YNAB has sent one yet.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().expect("rate limit lock poisoned")
+    }
+
+    /// GETs `path` under the API base path and parses the JSON body, retrying per the
+    /// retry policy. Fails fast once YNAB has reported the hourly quota as used up.
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        what: &str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T> {
+        let url = format!("{}{path}", self.configuration.base_path);
+        let mut attempt = 0;
+        loop {
+            if let Some(rate_limit) = self.rate_limit()
+                && rate_limit.is_exhausted()
+            {
+                return Err(RateLimitExceeded {
+                    rate_limit: Some(rate_limit),
+                    retry_after: None,
+                }
+                .into());
+            }
+
+            let mut request = self.configuration.client.get(&url).query(query);
+            if let Some(user_agent) = &self.configuration.user_agent {
+                request = request.header(reqwest::header::USER_AGENT, user_agent);
+            }
+            if let Some(token) = &self.configuration.bearer_access_token {
+                request = request.bearer_auth(token);
+            }
+
+            let can_retry = attempt < self.retry_policy.max_retries;
+            let slot = self
+                .request_slots
+                .acquire()
+                .await
+                .expect("request slots are never closed");
+            let wait = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    let rate_limit = RateLimit::from_headers(response.headers());
+                    if rate_limit.is_some() {
+                        *self.rate_limit.lock().expect("rate limit lock poisoned") = rate_limit;
+                    }
+                    let retry_after = retry_after(response.headers());
+                    let body = response.text().await.map_err(|err| Error::Api {
+                        what: what.to_string(),
+                        detail: format!("reading response body: {err}"),
+                    })?;
+
+                    if status.is_success() {
+                        return serde_json::from_str(&body)
+                            .shape_context(format!("{what} returned an unexpected response"));
+                    }
+
+                    let wait = retry_after.unwrap_or_else(|| self.retry_policy.backoff(attempt));
+                    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        let quota_used_up = rate_limit.is_some_and(|r| r.is_exhausted());
+                        if quota_used_up || !can_retry || wait > self.retry_policy.max_backoff {
+                            return Err(RateLimitExceeded {
+                                rate_limit,
+                                retry_after,
+                            }
+                            .into());
+                        }
+                    } else if !is_transient(status) || !can_retry {
+                        return Err(status_error(what, status, &body));
+                    }
+                    wait
+                }
+                Err(err) if (err.is_connect() || err.is_timeout()) && can_retry => {
+                    self.retry_policy.backoff(attempt)
+                }
+                Err(err) => {
+                    return Err(Error::Api {
+                        what: what.to_string(),
+                        detail: err.to_string(),
+                    });
+                }
+            };
+            drop(slot);
+
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl AsyncYnabApi for HttpYnabClient {
+    async fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        let resp: BudgetsResponse = self.get_json("get_budgets", "/budgets", &[]).await?;
+        Ok(resp.data.budgets)
+    }
+
+    async fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        let query: Vec<(&str, String)> = last_knowledge_of_server
+            .map(|k| ("last_knowledge_of_server", k.to_string()))
+            .into_iter()
+            .collect();
+        let resp: CategoriesResponse = self
+            .get_json(
+                &format!("get_categories for budget {budget_id}"),
+                &format!("/budgets/{}/categories", urlencode(budget_id)),
+                &query,
+            )
+            .await?;
+        Ok(Delta {
+            items: resp.data.category_groups,
+            server_knowledge: resp.data.server_knowledge,
+        })
+    }
+
+    async fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        let resp: AccountsResponse = self
+            .get_json(
+                &format!("get_accounts for budget {budget_id}"),
+                &format!("/budgets/{}/accounts", urlencode(budget_id)),
+                &[],
+            )
+            .await?;
+        Ok(resp.data.accounts)
+    }
+
+    async fn get_month_category(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        let month_str = month_param(month)?;
+        let resp: CategoryResponse = self
+            .get_json(
+                &format!(
+                    "get_month_category_by_id for budget {budget_id}, month {month_str}, category {category_id}"
+                ),
+                &format!(
+                    "/budgets/{}/months/{month_str}/categories/{}",
+                    urlencode(budget_id),
+                    urlencode(category_id)
+                ),
+                &[],
+            )
+            .await?;
+        Ok(resp.data.category)
+    }
+
+    async fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        let month_str = month_param(month)?;
+        let resp: MonthResponse = self
+            .get_json(
+                &format!("get_budget_month for budget {budget_id}, month {month_str}"),
+                &format!("/budgets/{}/months/{month_str}", urlencode(budget_id)),
+                &[],
+            )
+            .await?;
+        Ok(resp.data.month)
+    }
+
+    async fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        let since = since_date.format("%Y-%m-%d").to_string();
+        let mut query = vec![("since_date", since.clone())];
+        if let Some(knowledge) = last_knowledge_of_server {
+            query.push(("last_knowledge_of_server", knowledge.to_string()));
+        }
+        let resp: TransactionsResponse = self
+            .get_json(
+                &format!("get_transactions for budget {budget_id}, since_date {since}"),
+                &format!("/budgets/{}/transactions", urlencode(budget_id)),
+                &query,
+            )
+            .await?;
+        Ok(Delta {
+            items: resp.data.transactions,
+            server_knowledge: resp.data.server_knowledge,
+        })
+    }
+
+    async fn get_scheduled_transactions(
+        &self,
+        budget_id: &str,
+    ) -> Result<Vec<ScheduledTransaction>> {
+        let resp: ScheduledTransactionsResponse = self
+            .get_json(
+                &format!("get_scheduled_transactions for budget {budget_id}"),
+                &format!("/budgets/{}/scheduled_transactions", urlencode(budget_id)),
+                &[],
+            )
+            .await?;
+        Ok(resp.data.scheduled_transactions)
     }
 }
 
 impl YnabApi for HttpYnabClient {
     fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
-        let response = block_on(budgets_api::get_budgets(&self.configuration, None))
-            .map_err(|err| anyhow::anyhow!("get_budgets failed: {err:?}"))?;
-        let resp: BudgetsResponse = self.map_model(response, "BudgetSummaryResponse")?;
-        Ok(resp.data.budgets)
+        self.runtime.block_on(AsyncYnabApi::get_budgets(self))
     }
 
     fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
-        let response = block_on(categories_api::get_categories(
-            &self.configuration,
-            budget_id,
-            None,
-        ))
-        .map_err(|err| anyhow::anyhow!("get_categories failed for budget {budget_id}: {err:?}"))?;
-        let resp: CategoriesResponse = self.map_model(response, "CategoriesResponse")?;
-        Ok(resp.data.category_groups)
+        Ok(YnabApi::get_category_groups_delta(self, budget_id, None)?.items)
+    }
+
+    fn get_accounts(&self, budget_id: &str) -> Result<Vec<Account>> {
+        self.runtime
+            .block_on(AsyncYnabApi::get_accounts(self, budget_id))
     }
 
     fn get_month_category(
@@ -177,39 +1174,111 @@ impl YnabApi for HttpYnabClient {
         month: NaiveDate,
         category_id: &str,
     ) -> Result<Category> {
-        let first_of_month = NaiveDate::from_ymd_opt(month.year(), month.month(), 1)
-            .ok_or_else(|| anyhow::anyhow!("invalid month from {month}"))?;
-        let month_str = first_of_month.format("%Y-%m-%d").to_string();
-        let response = block_on(categories_api::get_month_category_by_id(
-            &self.configuration,
+        self.runtime.block_on(AsyncYnabApi::get_month_category(
+            self,
             budget_id,
-            month_str.clone(),
+            month,
             category_id,
         ))
-        .map_err(|err| {
-            anyhow::anyhow!(
-                "get_month_category_by_id failed for budget {budget_id}, month {month_str}, category {category_id}: {err:?}"
-            )
-        })?;
-        let resp: CategoryResponse = self.map_model(response, "CategoryResponse")?;
-        Ok(resp.data.category)
+    }
+
+    fn get_month_detail(&self, budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        self.runtime
+            .block_on(AsyncYnabApi::get_month_detail(self, budget_id, month))
+    }
+
+    fn get_month_details(&self, budget_id: &str, months: &[NaiveDate]) -> Result<Vec<MonthDetail>> {
+        let requests = months
+            .iter()
+            .map(|month| AsyncYnabApi::get_month_detail(self, budget_id, *month));
+        self.runtime.block_on(
+            stream::iter(requests)
+                .buffered(self.max_concurrent_requests)
+                .try_collect(),
+        )
+    }
+
+    fn get_month_categories_by_id(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        category_ids: &[&str],
+    ) -> Result<Vec<Category>> {
+        let requests = category_ids
+            .iter()
+            .map(|id| AsyncYnabApi::get_month_category(self, budget_id, month, id));
+        self.runtime.block_on(
+            stream::iter(requests)
+                .buffered(self.max_concurrent_requests)
+                .try_collect(),
+        )
     }
 
     fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
-        let since = since_date.format("%Y-%m-%d").to_string();
-        let response = block_on(transactions_api::get_transactions(
-            &self.configuration,
+        Ok(YnabApi::get_transactions_delta(self, budget_id, since_date, None)?.items)
+    }
+
+    fn get_scheduled_transactions(&self, budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        self.runtime
+            .block_on(AsyncYnabApi::get_scheduled_transactions(self, budget_id))
+    }
+
+    fn get_budget_data(
+        &self,
+        budget_id: &str,
+        month: NaiveDate,
+        since_date: NaiveDate,
+        with_accounts: bool,
+    ) -> Result<BudgetData> {
+        let accounts = async {
+            if with_accounts {
+                AsyncYnabApi::get_accounts(self, budget_id).await.map(Some)
+            } else {
+                Ok(None)
+            }
+        };
+        self.runtime.block_on(async {
+            let (category_groups, month, transactions, scheduled_transactions, accounts) = futures::try_join!(
+                AsyncYnabApi::get_category_groups_delta(self, budget_id, None),
+                AsyncYnabApi::get_month_detail(self, budget_id, month),
+                AsyncYnabApi::get_transactions_delta(self, budget_id, since_date, None),
+                AsyncYnabApi::get_scheduled_transactions(self, budget_id),
+                accounts,
+            )?;
+            Ok(BudgetData {
+                category_groups: category_groups.items,
+                month,
+                transactions: transactions.items,
+                scheduled_transactions,
+                accounts,
+            })
+        })
+    }
+
+    fn get_category_groups_delta(
+        &self,
+        budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        self.runtime
+            .block_on(AsyncYnabApi::get_category_groups_delta(
+                self,
+                budget_id,
+                last_knowledge_of_server,
+            ))
+    }
+
+    fn get_transactions_delta(
+        &self,
+        budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        self.runtime.block_on(AsyncYnabApi::get_transactions_delta(
+            self,
             budget_id,
-            Some(since.clone()),
-            None,
-            None,
+            since_date,
+            last_knowledge_of_server,
         ))
-        .map_err(|err| {
-            anyhow::anyhow!(
-                "get_transactions failed for budget {budget_id}, since_date {since}: {err:?}"
-            )
-        })?;
-        let resp: TransactionsResponse = self.map_model(response, "TransactionsResponse")?;
-        Ok(resp.data.transactions)
     }
 }
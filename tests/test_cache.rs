@@ -0,0 +1,519 @@
+use std::cell::RefCell;
+
+use chrono::NaiveDate;
+use crustynab::Result;
+use crustynab::cache::{CachingYnabApi, merge_category_groups, merge_transactions};
+use crustynab::cassette::{RecordingYnabApi, ReplayYnabApi};
+use crustynab::money::Milliunits;
+use crustynab::ynab::{
+    Account, BudgetSummary, Category, CategoryGroup, ClearedStatus, Delta, MonthDetail,
+    MonthSummary, ScheduledTransaction, Transaction, YnabApi,
+};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn category(id: &str, name: &str, balance: i64) -> Category {
+    Category {
+        id: id.into(),
+        name: name.into(),
+        category_group_name: Some("Essentials".into()),
+        budgeted: Milliunits(50000),
+        balance: Milliunits(balance),
+        goal_cadence: Some(1),
+        ..Default::default()
+    }
+}
+
+fn group(id: &str, name: &str, categories: Vec<Category>) -> CategoryGroup {
+    CategoryGroup {
+        id: id.into(),
+        name: name.into(),
+        hidden: false,
+        deleted: false,
+        categories,
+    }
+}
+
+fn transaction(id: &str, day: NaiveDate, amount: i64) -> Transaction {
+    Transaction {
+        id: id.into(),
+        date: day,
+        amount: Milliunits(amount),
+        payee_name: Some("Market".into()),
+        category_name: Some("Groceries".into()),
+        cleared: ClearedStatus::Cleared,
+        approved: true,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
+        ..Default::default()
+    }
+}
+
+/// In-memory stand-in for YNAB that answers delta requests from a change log.
+#[derive(Default)]
+struct FakeYnab {
+    server_knowledge: RefCell<i64>,
+    transaction_log: RefCell<Vec<(i64, Transaction)>>,
+    group_log: RefCell<Vec<(i64, CategoryGroup)>>,
+    calls: RefCell<Vec<String>>,
+}
+
+impl FakeYnab {
+    fn push_transaction(&self, txn: Transaction) {
+        let knowledge = {
+            let mut k = self.server_knowledge.borrow_mut();
+            *k += 1;
+            *k
+        };
+        self.transaction_log.borrow_mut().push((knowledge, txn));
+    }
+
+    fn push_group(&self, group: CategoryGroup) {
+        let knowledge = {
+            let mut k = self.server_knowledge.borrow_mut();
+            *k += 1;
+            *k
+        };
+        self.group_log.borrow_mut().push((knowledge, group));
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.borrow().clone()
+    }
+}
+
+impl YnabApi for FakeYnab {
+    fn get_budgets(&self) -> Result<Vec<BudgetSummary>> {
+        self.calls.borrow_mut().push("budgets".into());
+        Ok(vec![BudgetSummary {
+            id: "b1".into(),
+            name: "Budget".into(),
+            currency_format: None,
+        }])
+    }
+
+    fn get_category_groups(&self, budget_id: &str) -> Result<Vec<CategoryGroup>> {
+        Ok(self.get_category_groups_delta(budget_id, None)?.items)
+    }
+
+    fn get_accounts(&self, _budget_id: &str) -> Result<Vec<Account>> {
+        self.calls.borrow_mut().push("accounts".into());
+        Ok(Vec::new())
+    }
+
+    fn get_month_category(
+        &self,
+        _budget_id: &str,
+        month: NaiveDate,
+        category_id: &str,
+    ) -> Result<Category> {
+        self.calls
+            .borrow_mut()
+            .push(format!("month_category {month} {category_id}"));
+        Ok(category(category_id, "Groceries", 31500))
+    }
+
+    fn get_month_detail(&self, _budget_id: &str, month: NaiveDate) -> Result<MonthDetail> {
+        self.calls.borrow_mut().push(format!("month {month}"));
+        Ok(MonthDetail {
+            summary: MonthSummary {
+                month,
+                income: Milliunits(3200000),
+                budgeted: Milliunits(2950000),
+                activity: Milliunits(-1184250),
+                to_be_budgeted: Milliunits(250000),
+                age_of_money: Some(42),
+            },
+            categories: vec![category("c1", "Groceries", 31500)],
+        })
+    }
+
+    fn get_transactions(&self, budget_id: &str, since_date: NaiveDate) -> Result<Vec<Transaction>> {
+        Ok(self
+            .get_transactions_delta(budget_id, since_date, None)?
+            .items)
+    }
+
+    fn get_scheduled_transactions(&self, _budget_id: &str) -> Result<Vec<ScheduledTransaction>> {
+        self.calls
+            .borrow_mut()
+            .push("scheduled_transactions".into());
+        Ok(Vec::new())
+    }
+
+    fn get_category_groups_delta(
+        &self,
+        _budget_id: &str,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<CategoryGroup>> {
+        self.calls
+            .borrow_mut()
+            .push(format!("category_groups {last_knowledge_of_server:?}"));
+        let after = last_knowledge_of_server.unwrap_or(0);
+        let changes: Vec<CategoryGroup> = self
+            .group_log
+            .borrow()
+            .iter()
+            .filter(|(k, _)| *k > after)
+            .map(|(_, g)| g.clone())
+            .collect();
+        let items = match last_knowledge_of_server {
+            Some(_) => changes,
+            None => merge_category_groups(Vec::new(), changes),
+        };
+        Ok(Delta {
+            items,
+            server_knowledge: *self.server_knowledge.borrow(),
+        })
+    }
+
+    fn get_transactions_delta(
+        &self,
+        _budget_id: &str,
+        since_date: NaiveDate,
+        last_knowledge_of_server: Option<i64>,
+    ) -> Result<Delta<Transaction>> {
+        self.calls.borrow_mut().push(format!(
+            "transactions {since_date} {last_knowledge_of_server:?}"
+        ));
+        let after = last_knowledge_of_server.unwrap_or(0);
+        let mut latest: Vec<Transaction> = Vec::new();
+        for (k, txn) in self.transaction_log.borrow().iter() {
+            if *k > after && txn.date >= since_date {
+                latest.retain(|t| t.id != txn.id);
+                latest.push(txn.clone());
+            }
+        }
+        if last_knowledge_of_server.is_none() {
+            latest.retain(|t| !t.deleted);
+        }
+        Ok(Delta {
+            items: latest,
+            server_knowledge: *self.server_knowledge.borrow(),
+        })
+    }
+}
+
+fn transaction_ids(transactions: &[Transaction]) -> Vec<&str> {
+    transactions.iter().map(|t| t.id.as_str()).collect()
+}
+
+#[test]
+fn second_run_only_requests_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+    fake.push_transaction(transaction("t2", date(2024, 3, 12), -2000));
+
+    let first = CachingYnabApi::new(&fake, dir.path())
+        .get_transactions("b1", date(2024, 3, 10))
+        .unwrap();
+    assert_eq!(transaction_ids(&first), vec!["t1", "t2"]);
+
+    fake.push_transaction(transaction("t3", date(2024, 3, 13), -3000));
+    let second = CachingYnabApi::new(&fake, dir.path())
+        .get_transactions("b1", date(2024, 3, 10))
+        .unwrap();
+    assert_eq!(transaction_ids(&second), vec!["t1", "t2", "t3"]);
+
+    assert_eq!(
+        fake.calls(),
+        vec![
+            "transactions 2024-03-10 None",
+            "transactions 2024-03-10 Some(2)"
+        ]
+    );
+}
+
+#[test]
+fn deleted_transactions_are_removed_from_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+    fake.push_transaction(transaction("t2", date(2024, 3, 12), -2000));
+
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_transactions("b1", date(2024, 3, 10)).unwrap();
+
+    fake.push_transaction(Transaction {
+        deleted: true,
+        ..transaction("t1", date(2024, 3, 11), -1000)
+    });
+    fake.push_transaction(transaction("t2", date(2024, 3, 12), -2500));
+
+    let updated = api.get_transactions("b1", date(2024, 3, 10)).unwrap();
+    assert_eq!(transaction_ids(&updated), vec!["t2"]);
+    assert_eq!(updated[0].amount, Milliunits(-2500));
+}
+
+#[test]
+fn earlier_since_date_forces_full_refetch() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_transaction(transaction("t0", date(2024, 3, 2), -500));
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+
+    let api = CachingYnabApi::new(&fake, dir.path());
+    let week = api.get_transactions("b1", date(2024, 3, 10)).unwrap();
+    assert_eq!(transaction_ids(&week), vec!["t1"]);
+
+    let month = api.get_transactions("b1", date(2024, 3, 1)).unwrap();
+    assert_eq!(transaction_ids(&month), vec!["t0", "t1"]);
+
+    let later = api.get_transactions("b1", date(2024, 3, 5)).unwrap();
+    assert_eq!(transaction_ids(&later), vec!["t1"]);
+
+    assert_eq!(
+        fake.calls(),
+        vec![
+            "transactions 2024-03-10 None",
+            "transactions 2024-03-01 None",
+            "transactions 2024-03-05 Some(2)",
+        ]
+    );
+}
+
+#[test]
+fn category_group_deltas_are_merged() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![
+            category("c1", "Groceries", 100),
+            category("c2", "Rent", 200),
+        ],
+    ));
+    fake.push_group(group("g2", "Fun", vec![category("c3", "Books", 300)]));
+
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_category_groups("b1").unwrap();
+
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![
+            Category {
+                deleted: true,
+                ..category("c2", "Rent", 200)
+            },
+            category("c4", "Utilities", 400),
+        ],
+    ));
+    fake.push_group(CategoryGroup {
+        deleted: true,
+        ..group("g2", "Fun", vec![])
+    });
+
+    let groups = api.get_category_groups("b1").unwrap();
+    let summary: Vec<String> = groups
+        .iter()
+        .map(|g| {
+            let names: Vec<&str> = g.categories.iter().map(|c| c.name.as_str()).collect();
+            format!("{}: {}", g.name, names.join(", "))
+        })
+        .collect();
+    assert_eq!(summary, vec!["Essentials: Groceries, Utilities"]);
+    assert_eq!(
+        fake.calls(),
+        vec!["category_groups None", "category_groups Some(2)"]
+    );
+}
+
+#[test]
+fn month_categories_reused_while_budget_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![category("c1", "Groceries", 100)],
+    ));
+    let month = date(2024, 3, 10);
+
+    for _ in 0..2 {
+        let api = CachingYnabApi::new(&fake, dir.path());
+        api.get_category_groups("b1").unwrap();
+        api.get_month_category("b1", month, "c1").unwrap();
+        api.get_month_detail("b1", month).unwrap();
+    }
+
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_category_groups("b1").unwrap();
+    api.get_month_category("b1", month, "c1").unwrap();
+    api.get_month_detail("b1", month).unwrap();
+
+    let month_calls: Vec<String> = fake
+        .calls()
+        .into_iter()
+        .filter(|c| c.starts_with("month"))
+        .collect();
+    assert_eq!(
+        month_calls,
+        vec![
+            "month_category 2024-03-10 c1",
+            "month 2024-03-10",
+            "month_category 2024-03-10 c1",
+            "month 2024-03-10",
+        ]
+    );
+}
+
+#[test]
+fn scheduled_transactions_and_accounts_reused_while_budget_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![category("c1", "Groceries", 100)],
+    ));
+
+    for _ in 0..2 {
+        let api = CachingYnabApi::new(&fake, dir.path());
+        api.get_category_groups("b1").unwrap();
+        api.get_scheduled_transactions("b1").unwrap();
+        api.get_accounts("b1").unwrap();
+    }
+
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_category_groups("b1").unwrap();
+    api.get_scheduled_transactions("b1").unwrap();
+    api.get_accounts("b1").unwrap();
+
+    let calls = fake.calls();
+    let count = |name: &str| calls.iter().filter(|c| *c == name).count();
+    assert_eq!(count("scheduled_transactions"), 2);
+    assert_eq!(count("accounts"), 2);
+}
+
+#[test]
+fn budgets_are_cached_until_stale() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+
+    CachingYnabApi::new(&fake, dir.path())
+        .get_budgets()
+        .unwrap();
+    CachingYnabApi::new(&fake, dir.path())
+        .get_budgets()
+        .unwrap();
+    CachingYnabApi::new(&fake, dir.path())
+        .with_budgets_max_age(std::time::Duration::ZERO)
+        .get_budgets()
+        .unwrap();
+
+    assert_eq!(fake.calls(), vec!["budgets", "budgets"]);
+}
+
+#[test]
+fn unreadable_cache_files_are_refetched_with_a_warning() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    std::fs::write(dir.path().join("budgets.json"), "not json").unwrap();
+
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_budgets().unwrap();
+
+    assert_eq!(fake.calls(), vec!["budgets"]);
+    let warnings = api.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("budgets.json"), "{warnings:?}");
+    assert!(api.take_warnings().is_empty());
+}
+
+#[test]
+fn recording_through_a_warm_cache_replays() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let record_dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![category("c1", "Groceries", 100)],
+    ));
+    fake.push_transaction(transaction("t1", date(2024, 3, 11), -1000));
+    let month = date(2024, 3, 10);
+    CachingYnabApi::new(&fake, cache_dir.path())
+        .get_budget_data("b1", month, month, true)
+        .unwrap();
+
+    let recording = RecordingYnabApi::new(
+        CachingYnabApi::new(&fake, cache_dir.path()),
+        record_dir.path(),
+    );
+    let data = recording.get_budget_data("b1", month, month, true).unwrap();
+    assert_eq!(
+        fake.calls()
+            .iter()
+            .filter(|c| c.starts_with("month"))
+            .count(),
+        1
+    );
+
+    let replayed = ReplayYnabApi::new(record_dir.path())
+        .get_budget_data("b1", month, month, true)
+        .unwrap();
+    let as_json = |data: crustynab::ynab::BudgetData| {
+        serde_json::json!({
+            "groups": data.category_groups,
+            "month": data.month,
+            "transactions": data.transactions,
+            "scheduled": data.scheduled_transactions,
+            "accounts": data.accounts,
+        })
+    };
+    assert_eq!(as_json(data), as_json(replayed));
+}
+
+#[test]
+fn merge_transactions_orders_by_date() {
+    let merged = merge_transactions(
+        vec![transaction("b", date(2024, 3, 12), -1)],
+        vec![
+            transaction("a", date(2024, 3, 12), -2),
+            transaction("c", date(2024, 3, 11), -3),
+        ],
+    );
+    assert_eq!(transaction_ids(&merged), vec!["c", "a", "b"]);
+}
+
+#[test]
+fn batched_month_categories_only_fetch_uncached() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = FakeYnab::default();
+    fake.push_group(group(
+        "g1",
+        "Essentials",
+        vec![category("c1", "Groceries", 100)],
+    ));
+    let month = date(2024, 3, 10);
+
+    let api = CachingYnabApi::new(&fake, dir.path());
+    api.get_category_groups("b1").unwrap();
+    api.get_month_category("b1", month, "c1").unwrap();
+    let batch = api
+        .get_month_categories_by_id("b1", month, &["c2", "c1", "c3"])
+        .unwrap();
+
+    let ids: Vec<&str> = batch.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, vec!["c2", "c1", "c3"]);
+    let month_calls: Vec<String> = fake
+        .calls()
+        .into_iter()
+        .filter(|c| c.starts_with("month_category"))
+        .collect();
+    assert_eq!(
+        month_calls,
+        vec![
+            "month_category 2024-03-10 c1",
+            "month_category 2024-03-10 c2",
+            "month_category 2024-03-10 c3",
+        ]
+    );
+}
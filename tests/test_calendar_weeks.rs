@@ -1,11 +1,12 @@
-use chrono::{Datelike, NaiveDate};
+use chrono::{Datelike, NaiveDate, Weekday};
 use crustynab::calendar_weeks::{
-    month_week_for_date, month_weeks, partition_year_into_month_weeks,
+    Period, PeriodKind, WeekNumbering, month_week_for_date, month_weeks,
+    partition_year_into_month_weeks,
 };
 
 #[test]
 fn partition_2024_first_week_starts_jan_1() {
-    let weeks = partition_year_into_month_weeks(2024);
+    let weeks = partition_year_into_month_weeks(2024, Weekday::Sun);
     let first = &weeks[0];
     insta::assert_snapshot!(format!(
         "month={} start={} end={} week_number={}",
@@ -15,7 +16,7 @@ fn partition_2024_first_week_starts_jan_1() {
 
 #[test]
 fn partition_2024_covers_all_days() {
-    let weeks = partition_year_into_month_weeks(2024);
+    let weeks = partition_year_into_month_weeks(2024, Weekday::Sun);
     let mut all_dates: Vec<NaiveDate> = weeks.iter().flat_map(|w| w.dates()).collect();
     all_dates.sort();
     all_dates.dedup();
@@ -33,7 +34,7 @@ fn partition_2024_covers_all_days() {
 
 #[test]
 fn partition_2024_no_duplicate_dates() {
-    let weeks = partition_year_into_month_weeks(2024);
+    let weeks = partition_year_into_month_weeks(2024, Weekday::Sun);
     let all_dates: Vec<NaiveDate> = weeks.iter().flat_map(|w| w.dates()).collect();
     let mut sorted = all_dates.clone();
     sorted.sort();
@@ -44,13 +45,13 @@ fn partition_2024_no_duplicate_dates() {
 
 #[test]
 fn partition_2024_week_boundaries() {
-    let weeks = partition_year_into_month_weeks(2024);
+    let weeks = partition_year_into_month_weeks(2024, Weekday::Sun);
     let mut issues = Vec::new();
     for w in &weeks {
         let start_dow = w.week_start.weekday();
         let end_dow = w.week_end.weekday();
         let month_first = NaiveDate::from_ymd_opt(2024, w.month, 1).unwrap();
-        let start_ok = start_dow == chrono::Weekday::Sun || w.week_start == month_first;
+        let start_ok = start_dow == Weekday::Sun || w.week_start == month_first;
         let month_last_day = if w.month == 12 {
             NaiveDate::from_ymd_opt(2025, 1, 1)
         } else {
@@ -59,7 +60,7 @@ fn partition_2024_week_boundaries() {
         .unwrap()
         .pred_opt()
         .unwrap();
-        let end_ok = end_dow == chrono::Weekday::Sat || w.week_end == month_last_day;
+        let end_ok = end_dow == Weekday::Sat || w.week_end == month_last_day;
         if !start_ok || !end_ok {
             issues.push(format!(
                 "week {} month {} start={} ({:?}) end={} ({:?})",
@@ -73,7 +74,7 @@ fn partition_2024_week_boundaries() {
 
 #[test]
 fn month_weeks_march_2024() {
-    let weeks = month_weeks(2024, 3);
+    let weeks = month_weeks(2024, 3, Weekday::Sun);
     let summary: Vec<String> = weeks
         .iter()
         .map(|w| format!("week {} {}-{}", w.week_number, w.week_start, w.week_end))
@@ -84,7 +85,7 @@ fn month_weeks_march_2024() {
 #[test]
 fn month_week_for_date_2024_03_13() {
     let day = NaiveDate::from_ymd_opt(2024, 3, 13).unwrap();
-    let w = month_week_for_date(day).unwrap();
+    let w = month_week_for_date(day, Weekday::Sun).unwrap();
     insta::assert_snapshot!(format!(
         "month={} start={} end={} week_number={}",
         w.month, w.week_start, w.week_end, w.week_number
@@ -93,7 +94,7 @@ fn month_week_for_date_2024_03_13() {
 
 #[test]
 fn month_week_for_date_contains_date() {
-    let test_dates = vec![
+    let test_dates = [
         NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
         NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
         NaiveDate::from_ymd_opt(2024, 6, 15).unwrap(),
@@ -103,7 +104,7 @@ fn month_week_for_date_contains_date() {
     let results: Vec<String> = test_dates
         .iter()
         .map(|&d| {
-            let w = month_week_for_date(d).unwrap();
+            let w = month_week_for_date(d, Weekday::Sun).unwrap();
             let contains = w.week_start <= d && d <= w.week_end;
             format!(
                 "{d} contained={contains} week={}-{}",
@@ -116,7 +117,7 @@ fn month_week_for_date_contains_date() {
 
 #[test]
 fn week_number_matches_partition_order() {
-    let weeks = partition_year_into_month_weeks(2024);
+    let weeks = partition_year_into_month_weeks(2024, Weekday::Sun);
     let week_numbers: Vec<usize> = weeks.iter().map(|w| w.week_number).collect();
     let is_monotonic = week_numbers.windows(2).all(|pair| pair[0] <= pair[1]);
     insta::assert_snapshot!(format!(
@@ -127,3 +128,156 @@ fn week_number_matches_partition_order() {
     ));
     assert!(is_monotonic);
 }
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[test]
+fn every_week_start_partitions_leap_and_common_years() {
+    for year in [1900, 2000, 2023, 2024, 2100] {
+        let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+        for week_start in WEEKDAYS {
+            let weeks = partition_year_into_month_weeks(year, week_start);
+            let all_dates: Vec<NaiveDate> = weeks.iter().flat_map(|w| w.dates()).collect();
+            let expected: Vec<NaiveDate> = first.iter_days().take_while(|d| *d <= last).collect();
+            assert_eq!(all_dates, expected, "{year} starting {week_start}");
+            for w in &weeks {
+                let month_first = NaiveDate::from_ymd_opt(year, w.month, 1).unwrap();
+                assert!(
+                    w.week_start.weekday() == week_start || w.week_start == month_first,
+                    "{year} starting {week_start}: {w:?}"
+                );
+                assert!(
+                    w.week_end.weekday() == week_start.pred()
+                        || w.week_end.succ_opt().unwrap().day() == 1,
+                    "{year} starting {week_start}: {w:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn leap_day_week_for_every_week_start() {
+    let leap_day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    let results: Vec<String> = WEEKDAYS
+        .iter()
+        .map(|&week_start| {
+            let w = month_week_for_date(leap_day, week_start).unwrap();
+            format!(
+                "{week_start}: week {} {}-{}",
+                w.week_number, w.week_start, w.week_end
+            )
+        })
+        .collect();
+    insta::assert_snapshot!(results.join("\n"));
+}
+
+#[test]
+fn iso_week_numbers_cross_year_boundaries() {
+    let test_dates = [
+        NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 3, 13).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 12, 30).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2026, 12, 31).unwrap(),
+        NaiveDate::from_ymd_opt(2027, 1, 3).unwrap(),
+    ];
+    let results: Vec<String> = test_dates
+        .iter()
+        .map(|&d| {
+            let w = month_week_for_date(d, WeekNumbering::Iso.week_start(Weekday::Sun)).unwrap();
+            let (iso_week, iso_year) = w.week_of_year(WeekNumbering::Iso);
+            let (week, year) = w.week_of_year(WeekNumbering::Year);
+            format!(
+                "{d} {}-{}: ISO week {iso_week} of {iso_year}, week {week} of {year}",
+                w.week_start, w.week_end
+            )
+        })
+        .collect();
+    insta::assert_snapshot!(results.join("\n"));
+}
+
+#[test]
+fn every_period_kind_for_2024_08_14() {
+    let day = NaiveDate::from_ymd_opt(2024, 8, 14).unwrap();
+    let kinds = [
+        PeriodKind::MonthWeek,
+        PeriodKind::Month,
+        PeriodKind::Quarter,
+        PeriodKind::Year,
+        PeriodKind::Range {
+            start: NaiveDate::from_ymd_opt(2024, 7, 20).unwrap(),
+            end: NaiveDate::from_ymd_opt(2024, 8, 3).unwrap(),
+        },
+        PeriodKind::RollingDays(30),
+    ];
+    let results: Vec<String> = kinds
+        .iter()
+        .map(|kind| {
+            let p = kind
+                .period_for_date(day, Weekday::Sun, WeekNumbering::Year)
+                .unwrap();
+            format!(
+                "{kind:?}: {}-{} {:?} / {:?}, budget month {}",
+                p.start,
+                p.end,
+                p.name,
+                p.short_name,
+                p.budget_month(day)
+            )
+        })
+        .collect();
+    insta::assert_snapshot!(results.join("\n"));
+}
+
+#[test]
+fn impossible_periods_are_config_invalid() {
+    let day = NaiveDate::from_ymd_opt(2024, 8, 14).unwrap();
+    for kind in [
+        PeriodKind::RollingDays(0),
+        PeriodKind::Range {
+            start: day,
+            end: day.pred_opt().unwrap(),
+        },
+    ] {
+        let err = kind
+            .period_for_date(day, Weekday::Sun, WeekNumbering::Year)
+            .unwrap_err();
+        assert!(
+            matches!(err, crustynab::Error::ConfigInvalid(_)),
+            "{kind:?}"
+        );
+    }
+
+    let err = PeriodKind::RollingDays(u32::MAX)
+        .period_for_date(NaiveDate::MIN, Weekday::Sun, WeekNumbering::Year)
+        .unwrap_err();
+    assert!(matches!(err, crustynab::Error::ConfigInvalid(_)));
+}
+
+#[test]
+fn periods_list_every_budget_month_they_touch() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(
+        Period::between(date(2023, 11, 20), date(2024, 2, 1)).months(),
+        vec![
+            date(2023, 11, 1),
+            date(2023, 12, 1),
+            date(2024, 1, 1),
+            date(2024, 2, 1)
+        ]
+    );
+    assert_eq!(
+        Period::between(date(2024, 3, 10), date(2024, 3, 16)).months(),
+        vec![date(2024, 3, 1)]
+    );
+}
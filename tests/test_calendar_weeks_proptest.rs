@@ -1,6 +1,6 @@
 use chrono::{Datelike, Duration, NaiveDate, Weekday};
 use crustynab::calendar_weeks::{
-    month_week_for_date, month_weeks, partition_year_into_month_weeks,
+    PeriodKind, WeekNumbering, month_week_for_date, month_weeks, partition_year_into_month_weeks,
 };
 use proptest::prelude::*;
 
@@ -18,8 +18,12 @@ fn month_last_day(year: i32, month: u32) -> NaiveDate {
     .expect("valid previous day")
 }
 
-fn previous_sunday(day: NaiveDate) -> NaiveDate {
-    day - Duration::days(day.weekday().num_days_from_sunday() as i64)
+fn previous_week_start(day: NaiveDate, week_start: Weekday) -> NaiveDate {
+    day - Duration::days(day.weekday().days_since(week_start) as i64)
+}
+
+fn weekday_strategy() -> impl Strategy<Value = Weekday> {
+    (0_u8..7).prop_map(|n| Weekday::try_from(n).expect("valid weekday"))
 }
 
 fn full_date_strategy(min: NaiveDate, max: NaiveDate) -> impl Strategy<Value = NaiveDate> {
@@ -31,8 +35,8 @@ proptest! {
     #![proptest_config(ProptestConfig::with_cases(96))]
 
     #[test]
-    fn prop_partition_covers_year(year in 1900_i32..=2100_i32) {
-        let weeks = partition_year_into_month_weeks(year);
+    fn prop_partition_covers_year(year in 1900_i32..=2100_i32, week_start in weekday_strategy()) {
+        let weeks = partition_year_into_month_weeks(year, week_start);
         let all_days: Vec<NaiveDate> = weeks.iter().flat_map(|w| w.dates()).collect();
 
         let mut dedup = all_days.clone();
@@ -50,8 +54,12 @@ proptest! {
     }
 
     #[test]
-    fn prop_month_partition_covers_month(year in 1900_i32..=2100_i32, month in 1_u32..=12_u32) {
-        let weeks = month_weeks(year, month);
+    fn prop_month_partition_covers_month(
+        year in 1900_i32..=2100_i32,
+        month in 1_u32..=12_u32,
+        week_start in weekday_strategy(),
+    ) {
+        let weeks = month_weeks(year, month, week_start);
         let all_days: Vec<NaiveDate> = weeks.iter().flat_map(|w| w.dates()).collect();
 
         let mut dedup = all_days.clone();
@@ -69,18 +77,22 @@ proptest! {
     }
 
     #[test]
-    fn prop_week_invariants(year in 1900_i32..=2100_i32, month in 1_u32..=12_u32) {
-        let weeks = month_weeks(year, month);
+    fn prop_week_invariants(
+        year in 1900_i32..=2100_i32,
+        month in 1_u32..=12_u32,
+        week_start in weekday_strategy(),
+    ) {
+        let weeks = month_weeks(year, month, week_start);
         let month_first = date(year, month, 1);
         let month_last = month_last_day(year, month);
 
         for week in weeks {
-            let start_is_sunday = week.week_start.weekday() == Weekday::Sun;
-            let end_is_saturday = week.week_end.weekday() == Weekday::Sat;
+            let starts_week = week.week_start.weekday() == week_start;
+            let ends_week = week.week_end.weekday() == week_start.pred();
 
-            prop_assert!(start_is_sunday || week.week_start == month_first);
-            prop_assert!(end_is_saturday || week.week_end == month_last);
-            prop_assert!(start_is_sunday || end_is_saturday);
+            prop_assert!(starts_week || week.week_start == month_first);
+            prop_assert!(ends_week || week.week_end == month_last);
+            prop_assert!(starts_week || ends_week);
 
             let days = week.dates();
             prop_assert!(!days.is_empty());
@@ -94,24 +106,76 @@ proptest! {
 
     #[test]
     fn prop_month_week_for_date_contains_date(
-        day in full_date_strategy(date(1900, 1, 1), date(2100, 12, 31))
+        day in full_date_strategy(date(1900, 1, 1), date(2100, 12, 31)),
+        week_start in weekday_strategy(),
     ) {
-        let week = month_week_for_date(day).expect("date must resolve to month week");
+        let week = month_week_for_date(day, week_start).expect("date must resolve to month week");
 
         prop_assert_eq!(week.month, day.month());
         prop_assert!(week.week_start <= day && day <= week.week_end);
 
-        let month_partition = month_weeks(day.year(), day.month());
+        let month_partition = month_weeks(day.year(), day.month(), week_start);
         prop_assert!(month_partition.contains(&week));
     }
 
     #[test]
-    fn prop_week_number_matches_partition_order(year in 1900_i32..=2100_i32) {
-        let weeks = partition_year_into_month_weeks(year);
-        let year_anchor = previous_sunday(date(year, 1, 1));
+    fn prop_iso_numbering_matches_chrono(
+        day in full_date_strategy(date(1900, 1, 1), date(2100, 12, 31))
+    ) {
+        let week = month_week_for_date(day, WeekNumbering::Iso.week_start(Weekday::Sun))
+            .expect("date must resolve to month week");
+        let iso = day.iso_week();
+        prop_assert_eq!(
+            week.week_of_year(WeekNumbering::Iso),
+            (iso.week() as usize, iso.year())
+        );
+    }
+
+    #[test]
+    fn prop_periods_around_date_contain_it(
+        day in full_date_strategy(date(1900, 1, 1), date(2100, 12, 31)),
+        week_start in weekday_strategy(),
+        rolling_days in 1_u32..=400,
+    ) {
+        let kinds = [
+            PeriodKind::MonthWeek,
+            PeriodKind::Month,
+            PeriodKind::Quarter,
+            PeriodKind::Year,
+            PeriodKind::RollingDays(rolling_days),
+        ];
+        for kind in kinds {
+            let period = kind
+                .period_for_date(day, week_start, WeekNumbering::Year)
+                .expect("period must resolve");
+            prop_assert!(period.contains(day));
+            prop_assert_eq!(period.budget_month(day), day);
+        }
+
+        let rolling = PeriodKind::RollingDays(rolling_days)
+            .period_for_date(day, week_start, WeekNumbering::Year)
+            .expect("rolling period must resolve");
+        prop_assert_eq!((rolling.end - rolling.start).num_days() + 1, i64::from(rolling_days));
+
+        let quarter = PeriodKind::Quarter
+            .period_for_date(day, week_start, WeekNumbering::Year)
+            .expect("quarter must resolve");
+        prop_assert_eq!(quarter.start.day(), 1);
+        prop_assert_eq!((quarter.start.month() - 1) % 3, 0);
+        prop_assert_eq!(quarter.end.month(), quarter.start.month() + 2);
+        prop_assert_eq!(quarter.end.succ_opt().expect("next day").day(), 1);
+    }
+
+    #[test]
+    fn prop_week_number_matches_partition_order(
+        year in 1900_i32..=2100_i32,
+        week_start in weekday_strategy(),
+    ) {
+        let weeks = partition_year_into_month_weeks(year, week_start);
+        let year_anchor = previous_week_start(date(year, 1, 1), week_start);
 
         for week in weeks {
-            let anchor = previous_sunday(week.week_start);
+            let anchor = previous_week_start(week.week_start, week_start);
             let expected = ((anchor - year_anchor).num_days() / 7) as usize + 1;
             prop_assert_eq!(week.week_number, expected);
         }
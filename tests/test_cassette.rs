@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use chrono::{NaiveDate, Weekday};
+use crustynab::calendar_weeks::{PeriodKind, WeekNumbering};
+use crustynab::cassette::{RecordingYnabApi, ReplayYnabApi};
+use crustynab::report;
+use crustynab::ynab::{YnabApi, get_month_categories_for};
+use indexmap::IndexMap;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn basic_cassettes() -> &'static Path {
+    Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/cassettes/basic"
+    ))
+}
+
+fn fetch_everything(api: &dyn YnabApi) -> serde_json::Value {
+    let month = date(2024, 3, 10);
+    serde_json::json!({
+        "budgets": api.get_budgets().unwrap(),
+        "groups": api.get_category_groups("b1").unwrap(),
+        "accounts": api.get_accounts("b1").unwrap(),
+        "groups_delta": api.get_category_groups_delta("b1", None).unwrap(),
+        "month": api.get_month_category("b1", month, "cat-games").unwrap(),
+        "month_detail": api.get_month_detail("b1", month).unwrap(),
+        "transactions": api.get_transactions("b1", month).unwrap(),
+        "transactions_delta": api.get_transactions_delta("b1", month, None).unwrap(),
+        "scheduled_transactions": api.get_scheduled_transactions("b1").unwrap(),
+    })
+}
+
+#[test]
+fn recorded_responses_replay_identically() {
+    let dir = tempfile::tempdir().unwrap();
+    let live = ReplayYnabApi::new(basic_cassettes());
+
+    let recorded = fetch_everything(&RecordingYnabApi::new(live, dir.path()));
+    let replayed = fetch_everything(&ReplayYnabApi::new(dir.path()));
+
+    assert_eq!(recorded, replayed);
+}
+
+#[test]
+fn replay_without_cassette_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let api = ReplayYnabApi::new(dir.path());
+    let err = api.get_transactions("b1", date(2024, 3, 10)).unwrap_err();
+    assert!(matches!(err, crustynab::Error::NotFound(_)));
+}
+
+#[test]
+fn replayed_cassettes_build_report() {
+    let api = ReplayYnabApi::new(basic_cassettes());
+    let mut watch_list = IndexMap::new();
+    watch_list.insert("Essentials".to_string(), "#dfe7f5".to_string());
+    watch_list.insert("Fun".to_string(), "#f4dccb".to_string());
+
+    let budgets = api.get_budgets().unwrap();
+    let budget_id = report::get_budget_id(&budgets, "Test Budget").unwrap();
+    let groups = api.get_category_groups(&budget_id).unwrap();
+    let period = PeriodKind::MonthWeek
+        .period_for_date(date(2024, 3, 13), Weekday::Sun, WeekNumbering::Year)
+        .unwrap();
+
+    let categories_to_watch =
+        report::get_categories_to_watch(&groups, &watch_list, &IndexMap::new());
+    let month = api.get_month_detail(&budget_id, period.start).unwrap();
+    let month_categories =
+        get_month_categories_for(&api, &budget_id, &month, &categories_to_watch).unwrap();
+    let transactions = api.get_transactions(&budget_id, period.start).unwrap();
+    let scheduled = api.get_scheduled_transactions(&budget_id).unwrap();
+
+    let category_names: HashSet<String> = month_categories.iter().map(|c| c.name.clone()).collect();
+    let transactions_frame = report::relevant_transactions(
+        report::transactions_to_polars(&transactions).unwrap(),
+        &period,
+    );
+    let df = report::build_report_table(
+        report::categories_to_polars(&month_categories).unwrap(),
+        transactions_frame,
+        report::upcoming_transactions_to_polars(&scheduled, &groups, period.start, period.end)
+            .unwrap(),
+        &category_names,
+    )
+    .unwrap()
+    .collect()
+    .unwrap();
+    insta::assert_snapshot!(format!("{df}"));
+}
+
+#[test]
+fn budget_data_is_recorded_as_single_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let month = date(2024, 3, 10);
+    let recording = RecordingYnabApi::new(ReplayYnabApi::new(basic_cassettes()), dir.path());
+    let data = recording.get_budget_data("b1", month, month, true).unwrap();
+
+    let replay = ReplayYnabApi::new(dir.path());
+    let replayed = replay.get_budget_data("b1", month, month, true).unwrap();
+    assert_eq!(
+        serde_json::to_value(&data.transactions).unwrap(),
+        serde_json::to_value(replay.get_transactions("b1", month).unwrap()).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&data.month).unwrap(),
+        serde_json::to_value(&replayed.month).unwrap()
+    );
+    assert_eq!(
+        replayed.accounts.map(|accounts| accounts.len()),
+        data.accounts.map(|accounts| accounts.len())
+    );
+}
+
+#[test]
+fn month_details_are_recorded_one_cassette_per_month() {
+    let dir = tempfile::tempdir().unwrap();
+    let months = [date(2024, 3, 1)];
+    let recording = RecordingYnabApi::new(ReplayYnabApi::new(basic_cassettes()), dir.path());
+    let details = recording.get_month_details("b1", &months).unwrap();
+
+    let replayed = ReplayYnabApi::new(dir.path())
+        .get_month_detail("b1", months[0])
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&details).unwrap(),
+        serde_json::to_value([replayed]).unwrap()
+    );
+}
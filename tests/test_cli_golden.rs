@@ -5,11 +5,15 @@ use chrono::NaiveDate;
 use indexmap::IndexMap;
 use polars::prelude::*;
 
-use crustynab::calendar_weeks::month_week_for_date;
+use crustynab::calendar_weeks::{Period, PeriodKind};
 use crustynab::config::{Config, OutputFormat, SimpleOutputFormat};
+use crustynab::money::{CurrencyFormat, Milliunits};
 use crustynab::report;
-use crustynab::visual_report::build_visual_report_html;
-use crustynab::ynab::{Category, SubTransaction, Transaction};
+use crustynab::visual_report::{PlannedYear, ReportSections, build_visual_report_html};
+use crustynab::ynab::{
+    Account, AccountType, Category, ClearedStatus, Frequency, MonthSummary, ScheduledTransaction,
+    SubTransaction, Transaction,
+};
 
 fn make_categories() -> Vec<Category> {
     vec![
@@ -17,41 +21,39 @@ fn make_categories() -> Vec<Category> {
             id: "cat-groceries".into(),
             name: "Groceries".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 50000,
-            balance: 31500,
+            budgeted: Milliunits(50000),
+            balance: Milliunits(31500),
             goal_cadence: Some(1),
-            goal_target: Some(60000),
-            hidden: false,
+            goal_target: Some(Milliunits(60000)),
+            ..Default::default()
         },
         Category {
             id: "cat-rent".into(),
             name: "Rent".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 100000,
-            balance: 75000,
-            goal_cadence: Some(12),
-            goal_target: Some(120000),
-            hidden: false,
+            budgeted: Milliunits(100000),
+            balance: Milliunits(75000),
+            goal_cadence: Some(13),
+            goal_target: Some(Milliunits(120000)),
+            ..Default::default()
         },
         Category {
             id: "cat-books".into(),
             name: "Books".into(),
             category_group_name: Some("Fun".into()),
-            budgeted: 10000,
-            balance: 6000,
+            budgeted: Milliunits(10000),
+            balance: Milliunits(6000),
             goal_cadence: Some(1),
-            goal_target: None,
-            hidden: false,
+            ..Default::default()
         },
         Category {
             id: "cat-games".into(),
             name: "Games".into(),
             category_group_name: Some("Fun".into()),
-            budgeted: 20000,
-            balance: 17000,
+            budgeted: Milliunits(20000),
+            balance: Milliunits(17000),
             goal_cadence: Some(1),
-            goal_target: None,
-            hidden: false,
+            ..Default::default()
         },
     ]
 }
@@ -61,102 +63,268 @@ fn make_transactions() -> Vec<Transaction> {
         Transaction {
             id: "txn-1".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
-            amount: -12500,
+            amount: Milliunits(-12500),
             payee_name: Some("Market".into()),
             category_name: Some("Groceries".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
         Transaction {
             id: "txn-4".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 13).unwrap(),
-            amount: -10000,
+            amount: Milliunits(-10000),
             payee_name: Some("Market".into()),
             category_name: Some("Split".into()),
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
             subtransactions: vec![
                 SubTransaction {
-                    amount: -6000,
+                    amount: Milliunits(-6000),
                     payee_name: None,
                     category_name: Some("Groceries".into()),
+                    memo: None,
+                    transfer_account_id: None,
+                    deleted: false,
                 },
                 SubTransaction {
-                    amount: -4000,
+                    amount: Milliunits(-4000),
                     payee_name: None,
                     category_name: Some("Books".into()),
+                    memo: None,
+                    transfer_account_id: None,
+                    deleted: false,
                 },
             ],
+            ..Default::default()
         },
         Transaction {
             id: "txn-3".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
-            amount: -25000,
+            amount: Milliunits(-25000),
             payee_name: Some("Landlord".into()),
             category_name: Some("Rent".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
         Transaction {
             id: "txn-2".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
-            amount: -3000,
+            amount: Milliunits(-3000),
             payee_name: Some("Arcade".into()),
             category_name: Some("Games".into()),
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
+        },
+    ]
+}
+
+fn make_scheduled_transactions() -> Vec<ScheduledTransaction> {
+    vec![
+        ScheduledTransaction {
+            id: "sched-games".into(),
+            date_first: NaiveDate::from_ymd_opt(2024, 1, 16).unwrap(),
+            date_next: NaiveDate::from_ymd_opt(2024, 3, 16).unwrap(),
+            frequency: Frequency::Monthly,
+            amount: Milliunits(-5000),
+            memo: None,
+            flag_color: None,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            payee_name: Some("Game Pass".into()),
+            category_name: Some("Games".into()),
+            transfer_account_id: None,
             subtransactions: vec![],
+            deleted: false,
+        },
+        ScheduledTransaction {
+            id: "sched-groceries".into(),
+            date_first: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            date_next: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            frequency: Frequency::Weekly,
+            amount: Milliunits(-7000),
+            memo: None,
+            flag_color: None,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            payee_name: Some("Veg Box".into()),
+            category_name: Some("Groceries".into()),
+            transfer_account_id: None,
+            subtransactions: vec![],
+            deleted: false,
         },
     ]
 }
 
+fn make_accounts() -> Vec<Account> {
+    vec![
+        Account {
+            id: "a1".into(),
+            name: "Checking".into(),
+            account_type: AccountType::Checking,
+            on_budget: true,
+            closed: false,
+            balance: Milliunits(1250000),
+            cleared_balance: Milliunits(1200000),
+            uncleared_balance: Milliunits(50000),
+            deleted: false,
+        },
+        Account {
+            id: "a2".into(),
+            name: "Visa".into(),
+            account_type: AccountType::CreditCard,
+            on_budget: true,
+            closed: false,
+            balance: Milliunits(-310000),
+            cleared_balance: Milliunits(-300000),
+            uncleared_balance: Milliunits(-10000),
+            deleted: false,
+        },
+    ]
+}
+
+fn make_month_summary() -> MonthSummary {
+    MonthSummary {
+        month: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        income: Milliunits(3200000),
+        budgeted: Milliunits(2950000),
+        activity: Milliunits(-1184250),
+        to_be_budgeted: Milliunits(250000),
+        age_of_money: Some(42),
+    }
+}
+
 fn make_config(show_all_rows: bool) -> Config {
     let mut watch_list = IndexMap::new();
     watch_list.insert("Essentials".to_string(), "#dfe7f5".to_string());
     watch_list.insert("Fun".to_string(), "#f4dccb".to_string());
 
     Config {
-        budget_name: "Test Budget".to_string(),
-        personal_access_token: "token".to_string(),
+        budget_name: Some("Test Budget".to_string()),
+        personal_access_token: Some("token".into()),
+        personal_access_token_from: Default::default(),
         category_group_watch_list: watch_list,
+        category_rules: Default::default(),
+        budgets: vec![],
         resolution_date: Some(NaiveDate::from_ymd_opt(2024, 3, 13).unwrap()),
+        period: Default::default(),
+        week_start: chrono::Weekday::Sun,
+        week_numbering: Default::default(),
         show_all_rows,
-        output_format: OutputFormat::Simple(SimpleOutputFormat::PolarsPrint),
+        output_formats: vec![OutputFormat::Simple(SimpleOutputFormat::PolarsPrint)],
+        show_net_worth: true,
+        currency_format: Default::default(),
+        source: Default::default(),
     }
 }
 
-fn run_report(cfg: &Config) -> Result<(String, LazyFrame, LazyFrame)> {
+struct ReportOutput {
+    header: String,
+    month_summary: LazyFrame,
+    report_display: LazyFrame,
+    totals: LazyFrame,
+    upcoming: LazyFrame,
+    net_worth: Option<LazyFrame>,
+}
+
+fn report_period(cfg: &Config) -> crustynab::Result<Period> {
+    let resolution_date = cfg.resolution_date.expect("test has resolution_date");
+    cfg.period
+        .period_for_date(resolution_date, cfg.first_weekday(), cfg.week_numbering)
+}
+
+fn run_report(cfg: &Config) -> Result<ReportOutput> {
     let categories = make_categories();
     let transactions = make_transactions();
 
-    let resolution_date = cfg.resolution_date.expect("test has resolution_date");
-    let report_week = month_week_for_date(resolution_date)?;
-    let report_start = report_week.week_start;
-    let report_end = report_week.week_end;
+    let period = report_period(cfg)?;
+    let report_start = period.start;
+    let report_end = period.end;
 
+    let month_summary = report::build_month_summary_table(&make_month_summary())?;
+    // Every other month the period touches budgets the same amounts as March.
+    let other_months = vec![make_categories(); period.months().len() - 1];
+    let categories = report::sum_budgeted_over_months(categories, &other_months);
     let categories_budgeted = report::categories_to_polars(&categories)?;
+    let net_worth = cfg.show_net_worth.then(|| {
+        report::build_net_worth_table(
+            report::accounts_to_polars(&make_accounts()).unwrap(),
+            &transactions,
+            report_start,
+            report_end,
+        )
+    });
     let transactions_frame = report::transactions_to_polars(&transactions)?;
-    let transactions_frame =
-        report::relevant_transactions(transactions_frame, report_start, report_end);
+    let transactions_frame = report::relevant_transactions(transactions_frame, &period);
+
+    let upcoming_frame = report::upcoming_transactions_to_polars(
+        &make_scheduled_transactions(),
+        &[],
+        report_start,
+        report_end,
+    )?;
 
     let cat_names: HashSet<String> = categories.iter().map(|c| c.name.clone()).collect();
-    let report_table =
-        report::build_report_table(categories_budgeted, transactions_frame, &cat_names)?;
+    let report_table = report::build_report_table(
+        categories_budgeted,
+        transactions_frame,
+        upcoming_frame.clone(),
+        &cat_names,
+    )?;
+    let report_table = report::apply_category_rules(report_table, &cfg.category_rules)?;
+    let upcoming_table = report::apply_category_rules(
+        report::build_upcoming_table(upcoming_frame, &cat_names),
+        &cfg.category_rules,
+    )?;
 
     let report_table_full = report_table.clone();
     let report_table_display = if cfg.show_all_rows {
         report_table
     } else {
-        report_table.filter(col("spent").neq(lit(0.0)))
+        report_table.filter(
+            col("spent")
+                .neq(lit(0_i64))
+                .or(col("upcoming").neq(lit(0_i64))),
+        )
     };
 
     let category_group_totals =
         report::build_category_group_totals_table(report_table_full.clone())?;
+    let report_table_display =
+        report::to_display_units(report_table_display, &CurrencyFormat::default())?;
+    let category_group_totals =
+        report::to_display_units(category_group_totals, &CurrencyFormat::default())?;
+    let month_summary = report::to_display_units(month_summary, &CurrencyFormat::default())?;
+    let upcoming = report::to_display_units(upcoming_table, &CurrencyFormat::default())?;
+    let net_worth = net_worth
+        .map(|table| report::to_display_units(table, &CurrencyFormat::default()))
+        .transpose()?;
 
-    let week_year = report_week.week_start.year();
-    let week_number = report_week.week_number;
     let start_label = report_start.format("%A %Y-%m-%d");
     let end_label = report_end.format("%A %Y-%m-%d");
     let header = format!(
-        "Week {week_number} of {week_year}, starting on {start_label} and ending on {end_label}"
+        "{}, starting on {start_label} and ending on {end_label}",
+        period.name
     );
 
-    use chrono::Datelike;
-    Ok((header, report_table_display, category_group_totals))
+    Ok(ReportOutput {
+        header,
+        month_summary,
+        report_display: report_table_display,
+        totals: category_group_totals,
+        upcoming,
+        net_worth,
+    })
 }
 
 fn write_csv_string(df: &mut DataFrame) -> String {
@@ -169,8 +337,8 @@ fn format_short_date(date: NaiveDate) -> String {
     let formatted = date.format("%b %d").to_string();
     if let Some(space_pos) = formatted.rfind(' ') {
         let (prefix, day_part) = formatted.split_at(space_pos + 1);
-        if day_part.starts_with('0') {
-            return format!("{}{}", prefix, &day_part[1..]);
+        if let Some(day) = day_part.strip_prefix('0') {
+            return format!("{prefix}{day}");
         }
     }
     formatted
@@ -179,70 +347,141 @@ fn format_short_date(date: NaiveDate) -> String {
 #[test]
 fn golden_polars_print() {
     let cfg = make_config(true);
-    let (header, report_display, totals) = run_report(&cfg).unwrap();
+    let output = run_report(&cfg).unwrap();
     unsafe { std::env::set_var("POLARS_FMT_MAX_ROWS", "-1") };
-    let df = report_display.collect().unwrap();
-    let totals_df = totals.collect().unwrap();
-    let output = format!("{header}\n{df}\nCategory group totals\n{totals_df}\n");
+    let month_summary_df = output.month_summary.collect().unwrap();
+    let df = output.report_display.collect().unwrap();
+    let totals_df = output.totals.collect().unwrap();
+    let upcoming_df = output.upcoming.collect().unwrap();
+    let net_worth_df = output.net_worth.unwrap().collect().unwrap();
+    let output = format!(
+        "{}\nBudget month\n{month_summary_df}\n{df}\nCategory group totals\n{totals_df}\nUpcoming in period\n{upcoming_df}\nNet worth\n{net_worth_df}\n",
+        output.header
+    );
     insta::assert_snapshot!(output);
 }
 
 #[test]
 fn golden_csv_print() {
     let cfg = make_config(true);
-    let (header, report_display, totals) = run_report(&cfg).unwrap();
-    let mut df = report_display.collect().unwrap();
-    let mut totals_df = totals.collect().unwrap();
+    let output = run_report(&cfg).unwrap();
+    let mut df = output.report_display.collect().unwrap();
+    let mut totals_df = output.totals.collect().unwrap();
+    let mut upcoming_df = output.upcoming.collect().unwrap();
     let csv = write_csv_string(&mut df);
     let totals_csv = write_csv_string(&mut totals_df);
-    let output = format!("{header}\n{csv}category_group_totals\n{totals_csv}");
+    let upcoming_csv = write_csv_string(&mut upcoming_df);
+    let net_worth_csv = write_csv_string(&mut output.net_worth.unwrap().collect().unwrap());
+    let month_summary_csv = write_csv_string(&mut output.month_summary.collect().unwrap());
+    let output = format!(
+        "{}\nbudget_month\n{month_summary_csv}{csv}category_group_totals\n{totals_csv}upcoming_in_period\n{upcoming_csv}net_worth\n{net_worth_csv}",
+        output.header
+    );
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn golden_quarter_csv_print() {
+    let cfg = Config {
+        period: PeriodKind::Quarter,
+        ..make_config(false)
+    };
+    let output = run_report(&cfg).unwrap();
+    let mut df = output.report_display.collect().unwrap();
+    let mut totals_df = output.totals.collect().unwrap();
+    let csv = write_csv_string(&mut df);
+    let totals_csv = write_csv_string(&mut totals_df);
+    let output = format!(
+        "{}\n{csv}category_group_totals\n{totals_csv}",
+        output.header
+    );
     insta::assert_snapshot!(output);
 }
 
 #[test]
 fn golden_csv_output_files() {
     let cfg = make_config(true);
-    let (_, report_display, totals) = run_report(&cfg).unwrap();
-    let mut df = report_display.collect().unwrap();
-    let mut totals_df = totals.collect().unwrap();
+    let output = run_report(&cfg).unwrap();
+    let mut df = output.report_display.collect().unwrap();
+    let mut totals_df = output.totals.collect().unwrap();
+    let mut upcoming_df = output.upcoming.collect().unwrap();
     let csv = write_csv_string(&mut df);
     let totals_csv = write_csv_string(&mut totals_df);
+    let upcoming_csv = write_csv_string(&mut upcoming_df);
     insta::assert_snapshot!("golden_csv_report", csv);
     insta::assert_snapshot!("golden_csv_totals", totals_csv);
+    insta::assert_snapshot!("golden_csv_upcoming", upcoming_csv);
+    let net_worth_csv = write_csv_string(&mut output.net_worth.unwrap().collect().unwrap());
+    insta::assert_snapshot!("golden_csv_net_worth", net_worth_csv);
+    let month_summary_csv = write_csv_string(&mut output.month_summary.collect().unwrap());
+    insta::assert_snapshot!("golden_csv_budget_month", month_summary_csv);
 }
 
-#[test]
-fn golden_visual_output() {
-    let cfg = make_config(true);
-    let resolution_date = cfg.resolution_date.unwrap();
-    let report_week = month_week_for_date(resolution_date).unwrap();
+fn visual_report(cfg: &Config) -> String {
+    let period = report_period(cfg).unwrap();
 
     let categories = make_categories();
     let transactions = make_transactions();
+    let other_months = vec![make_categories(); period.months().len() - 1];
+    let categories = report::sum_budgeted_over_months(categories, &other_months);
     let categories_budgeted = report::categories_to_polars(&categories).unwrap();
     let transactions_frame = report::transactions_to_polars(&transactions).unwrap();
-    let transactions_frame = report::relevant_transactions(
-        transactions_frame,
-        report_week.week_start,
-        report_week.week_end,
-    );
+    let transactions_frame = report::relevant_transactions(transactions_frame, &period);
+    let upcoming_frame = report::upcoming_transactions_to_polars(
+        &make_scheduled_transactions(),
+        &[],
+        period.start,
+        period.end,
+    )
+    .unwrap();
     let cat_names: HashSet<String> = categories.iter().map(|c| c.name.clone()).collect();
-    let report_table =
-        report::build_report_table(categories_budgeted, transactions_frame, &cat_names).unwrap();
+    let report_table = report::build_report_table(
+        categories_budgeted,
+        transactions_frame,
+        upcoming_frame.clone(),
+        &cat_names,
+    )
+    .unwrap();
 
     use chrono::Datelike;
-    let week_number = report_week.week_number;
-    let week_short_start = format_short_date(report_week.week_start);
-    let week_short_end = format_short_date(report_week.week_end);
-    let week_label = format!("Week {week_number} ({week_short_start} - {week_short_end})");
+    let short_start = format_short_date(period.start);
+    let short_end = format_short_date(period.end);
+    let week_label = format!("{} ({short_start} - {short_end})", period.short_name);
 
-    let html = build_visual_report_html(
+    build_visual_report_html(
         report_table,
+        ReportSections {
+            month_summary: Some(report::build_month_summary_table(&make_month_summary()).unwrap()),
+            upcoming: Some(report::build_upcoming_table(upcoming_frame, &cat_names)),
+            net_worth: Some(report::build_net_worth_table(
+                report::accounts_to_polars(&make_accounts()).unwrap(),
+                &transactions,
+                period.start,
+                period.end,
+            )),
+        },
         &cfg.category_group_watch_list,
         &week_label,
-        report_week.week_start.year(),
+        PlannedYear {
+            year: period.start.year(),
+            budgeted_months: period.months().len() as u32,
+        },
         true,
+        &CurrencyFormat::default(),
     )
-    .unwrap();
-    insta::assert_snapshot!(html);
+    .unwrap()
+}
+
+#[test]
+fn golden_visual_output() {
+    insta::assert_snapshot!(visual_report(&make_config(true)));
+}
+
+#[test]
+fn golden_quarter_visual_output() {
+    let cfg = Config {
+        period: PeriodKind::Quarter,
+        ..make_config(true)
+    };
+    insta::assert_snapshot!(visual_report(&cfg));
 }
@@ -0,0 +1,959 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{NaiveDate, Weekday};
+use crustynab::calendar_weeks::PeriodKind;
+use crustynab::config::{
+    ConfigOverrides, CurrencyFormatOverride, OutputFormat, deep_merge, load_config,
+    load_config_with_overrides, load_layered_config,
+};
+use crustynab::money::CurrencyFormat;
+
+fn write_config(contents: &str) -> tempfile::NamedTempFile {
+    write_config_as(".json", contents)
+}
+
+fn write_config_as(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn currency_format_override_keeps_unset_fields() {
+    let budget_format = CurrencyFormat {
+        iso_code: "CAD".into(),
+        currency_symbol: "$".into(),
+        ..CurrencyFormat::default()
+    };
+    let override_format = CurrencyFormatOverride {
+        currency_symbol: Some("CA$".into()),
+        ..CurrencyFormatOverride::default()
+    };
+
+    let applied = override_format.apply(budget_format.clone());
+    assert_eq!(
+        applied,
+        CurrencyFormat {
+            currency_symbol: "CA$".into(),
+            ..budget_format
+        }
+    );
+}
+
+#[test]
+fn currency_format_override_is_read_from_config() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {},
+            "currencyFormat": {"currencySymbol": "€", "symbolFirst": false}
+        }"#,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    let applied = cfg.currency_format.apply(CurrencyFormat::default());
+    assert_eq!(applied.format(-1234.5), "-1,234.50€");
+}
+
+#[test]
+fn week_start_and_numbering_are_read_from_config() {
+    let config = |extra: &str| {
+        write_config(&format!(
+            r#"{{"budgetName": "Budget", "personalAccessToken": "token"{extra}}}"#
+        ))
+    };
+    let default = load_config(config("").path()).unwrap();
+    assert_eq!(default.week_start, Weekday::Sun);
+    let monday = load_config(config(r#", "weekStart": "Monday""#).path()).unwrap();
+    assert_eq!(monday.week_start, Weekday::Mon);
+    let short = load_config(config(r#", "weekStart": "tue""#).path()).unwrap();
+    assert_eq!(short.week_start, Weekday::Tue);
+
+    let iso =
+        load_config(config(r#", "weekStart": "Sun", "weekNumbering": "iso""#).path()).unwrap();
+    assert_eq!(iso.first_weekday(), Weekday::Mon);
+
+    let err = load_config(config(r#", "weekStart": "Someday""#).path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(keys, vec!["weekStart"]);
+}
+
+#[test]
+fn period_is_read_in_every_form() {
+    let period = |value: &str| {
+        let file = write_config(&format!(
+            r#"{{"budgetName": "Budget", "personalAccessToken": "token", "period": {value}}}"#
+        ));
+        load_config(file.path()).map(|cfg| cfg.period)
+    };
+    assert_eq!(period(r#""monthWeek""#).unwrap(), PeriodKind::MonthWeek);
+    assert_eq!(period(r#""quarter""#).unwrap(), PeriodKind::Quarter);
+    assert_eq!(
+        period(r#"{"range": {"start": "2024-03-01", "end": "2024-03-15"}}"#).unwrap(),
+        PeriodKind::Range {
+            start: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            end: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+        }
+    );
+    assert_eq!(
+        period(r#"{"rollingDays": 30}"#).unwrap(),
+        PeriodKind::RollingDays(30)
+    );
+
+    for bad in [
+        r#""fortnight""#,
+        r#"{"rollingDays": 0}"#,
+        r#"{"range": {"start": "2024-03-15", "end": "2024-03-01"}}"#,
+    ] {
+        let keys: Vec<String> = problem_locations(period(bad).unwrap_err())
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        assert_eq!(keys, vec!["period"], "{bad}");
+    }
+}
+
+#[test]
+fn malformed_config_is_config_invalid() {
+    let file = write_config("{ not json");
+    let err = load_config(file.path()).unwrap_err();
+    assert!(matches!(err, crustynab::Error::ConfigInvalid(_)));
+}
+
+#[test]
+fn single_budget_config_reports_on_one_budget() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Personal",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"}
+        }"##,
+    );
+    let budgets = load_config(file.path()).unwrap().budget_configs().unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].budget_name, "Personal");
+    assert_eq!(
+        budgets[0]
+            .category_group_watch_list
+            .get("Essentials")
+            .map(String::as_str),
+        Some("#dfe7f5")
+    );
+}
+
+#[test]
+fn budgets_list_keeps_each_watch_list() {
+    let file = write_config(
+        r##"{
+            "personalAccessToken": "token",
+            "budgets": [
+                {"budgetName": "Personal", "categoryGroupWatchList": {"Fun": "#f4dccb"}},
+                {"budgetName": "Joint", "categoryGroupWatchList": {"Bills": "#dfe7f5"}}
+            ]
+        }"##,
+    );
+    let budgets = load_config(file.path()).unwrap().budget_configs().unwrap();
+    let summary: Vec<(&str, Vec<&str>)> = budgets
+        .iter()
+        .map(|b| {
+            (
+                b.budget_name.as_str(),
+                b.category_group_watch_list
+                    .keys()
+                    .map(String::as_str)
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("Personal", vec!["Fun"]), ("Joint", vec!["Bills"])]
+    );
+}
+
+#[test]
+fn budget_name_and_budgets_together_are_a_config_problem() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Personal",
+            "personalAccessToken": "token",
+            "budgets": [{"budgetName": "Joint", "categoryGroupWatchList": {}}]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(keys, vec!["budgets".to_string()]);
+}
+
+#[test]
+fn missing_budget_is_a_config_problem() {
+    let file = write_config(r#"{"personalAccessToken": "token"}"#);
+    let err = load_config(file.path()).unwrap_err();
+    assert_eq!(
+        problem_locations(err),
+        vec![(String::new(), "budgetName".to_string())]
+    );
+}
+
+#[test]
+fn outputs_writing_each_others_section_files_are_a_config_problem() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "showNetWorth": true,
+            "outputFormat": [
+                {"csv_output": "report.csv"},
+                {"visual_output": "report_upcoming.csv"},
+                {"csv_output": "other.csv"},
+                {"csv_output": "report_net.csv"}
+            ]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(keys, vec!["outputFormat[1].visual_output".to_string()]);
+
+    let csv = OutputFormat::CsvFile {
+        csv_output: PathBuf::from("out/report.csv"),
+    };
+    assert_eq!(
+        csv.files(true),
+        vec![
+            PathBuf::from("out/report.csv"),
+            PathBuf::from("out/report_category_group_totals.csv"),
+            PathBuf::from("out/report_upcoming.csv"),
+            PathBuf::from("out/report_budget_month.csv"),
+            PathBuf::from("out/report_net_worth.csv"),
+        ]
+    );
+    assert_eq!(csv.files(false).len(), 4);
+}
+
+#[test]
+fn budgets_whose_output_files_clash_are_a_config_problem() {
+    let file = write_config(
+        r#"{
+            "personalAccessToken": "token",
+            "budgets": [
+                {"budgetName": "Joint", "categoryGroupWatchList": {}},
+                {"budgetName": "Personal", "categoryGroupWatchList": {}},
+                {"budgetName": "joint!", "categoryGroupWatchList": {}}
+            ],
+            "outputFormat": [{"csv_output": "report.csv"}, "polars_print"]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    assert_eq!(
+        problem_locations(err)
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect::<Vec<_>>(),
+        vec!["budgets[2].budgetName".to_string()]
+    );
+
+    let file = write_config(
+        r#"{
+            "personalAccessToken": "token",
+            "budgets": [{"budgetName": "Joint", "categoryGroupWatchList": {}}, {"budgetName": "joint!", "categoryGroupWatchList": {}}],
+            "outputFormat": "csv_print"
+        }"#,
+    );
+    assert!(load_config(file.path()).is_ok());
+}
+
+#[test]
+fn output_files_are_named_per_budget() {
+    let csv = OutputFormat::CsvFile {
+        csv_output: PathBuf::from("out/report.csv"),
+    };
+    let visual = OutputFormat::VisualFile {
+        visual_output: PathBuf::from("report"),
+    };
+    let paths: Vec<PathBuf> = [
+        csv.for_budget("Joint Household"),
+        visual.for_budget("Personal!"),
+        csv.for_budget("🏠"),
+    ]
+    .into_iter()
+    .map(|format| match format {
+        OutputFormat::CsvFile { csv_output } => csv_output,
+        OutputFormat::VisualFile { visual_output } => visual_output,
+        OutputFormat::Simple(_) => unreachable!(),
+    })
+    .collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("out/report_joint_household.csv"),
+            PathBuf::from("report_personal"),
+            PathBuf::from("out/report_budget.csv"),
+        ]
+    );
+}
+
+#[test]
+fn token_sources_are_read_from_config() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessTokenFrom": {"command": ["sh", "-c", "echo from-command"]}
+        }"#,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    assert!(cfg.personal_access_token.is_none());
+    assert_eq!(
+        cfg.personal_access_token().unwrap().expose(),
+        "from-command"
+    );
+}
+
+#[test]
+fn toml_config_is_read_by_extension() {
+    let file = write_config_as(
+        ".toml",
+        r##"
+budgetName = "Budget"
+personalAccessToken = "token"
+resolutionDate = 2024-03-13
+showAllRows = true
+outputFormat = { visual_output = "report.html" }
+
+[categoryGroupWatchList]
+Essentials = "#dfe7f5"
+"##,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    assert_eq!(cfg.budget_name.as_deref(), Some("Budget"));
+    assert_eq!(
+        cfg.resolution_date,
+        chrono::NaiveDate::from_ymd_opt(2024, 3, 13)
+    );
+    assert!(cfg.show_all_rows);
+    assert!(matches!(
+        cfg.output_formats.as_slice(),
+        [OutputFormat::VisualFile { .. }]
+    ));
+    assert!(cfg.category_group_watch_list.contains_key("Essentials"));
+}
+
+#[test]
+fn yaml_config_is_read_by_extension() {
+    let file = write_config_as(
+        ".yml",
+        r##"
+personalAccessToken: token
+budgets:
+  - budgetName: Personal
+    categoryGroupWatchList:
+      Fun: "#f4dccb"
+outputFormat: csv_print
+"##,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    assert_eq!(cfg.budget_configs().unwrap()[0].budget_name, "Personal");
+    assert!(matches!(
+        cfg.output_formats.as_slice(),
+        [OutputFormat::Simple(_)]
+    ));
+}
+
+#[test]
+fn unknown_config_extension_is_config_invalid() {
+    let file = write_config_as(".ini", "budgetName = Budget");
+    let err = load_config(file.path()).unwrap_err();
+    assert!(matches!(err, crustynab::Error::ConfigInvalid(_)));
+}
+
+#[test]
+fn layered_configs_are_deep_merged() {
+    let base = write_config_as(
+        ".toml",
+        r##"
+budgetName = "Budget"
+showAllRows = true
+
+[categoryGroupWatchList]
+Essentials = "#dfe7f5"
+Fun = "#f4dccb"
+
+[currencyFormat]
+currencySymbol = "€"
+symbolFirst = false
+"##,
+    );
+    let secrets = write_config_as(".yaml", "personalAccessToken: token\n");
+    let local = write_config(
+        r##"{
+            "categoryGroupWatchList": {"Fun": "#000000"},
+            "currencyFormat": {"symbolFirst": true},
+            "showAllRows": false
+        }"##,
+    );
+
+    let cfg = load_layered_config(&[base.path(), secrets.path(), local.path()]).unwrap();
+    assert_eq!(cfg.personal_access_token().unwrap().expose(), "token");
+    assert!(!cfg.show_all_rows);
+    let watch_list: Vec<(&str, &str)> = cfg
+        .category_group_watch_list
+        .iter()
+        .map(|(name, color)| (name.as_str(), color.as_str()))
+        .collect();
+    assert_eq!(
+        watch_list,
+        vec![("Essentials", "#dfe7f5"), ("Fun", "#000000")]
+    );
+    assert_eq!(cfg.currency_format.currency_symbol.as_deref(), Some("€"));
+    assert_eq!(cfg.currency_format.symbol_first, Some(true));
+}
+
+#[test]
+fn deep_merge_replaces_lists_and_scalars() {
+    let mut base = serde_json::json!({"a": {"b": 1, "c": [1, 2]}, "d": "x"});
+    deep_merge(
+        &mut base,
+        serde_json::json!({"a": {"c": [3]}, "d": {"e": true}}),
+    );
+    assert_eq!(
+        base,
+        serde_json::json!({"a": {"b": 1, "c": [3]}, "d": {"e": true}})
+    );
+}
+
+fn problem_locations(err: crustynab::Error) -> Vec<(String, String)> {
+    let crustynab::Error::ConfigProblems(problems) = err else {
+        panic!("expected config problems, got {err}");
+    };
+    problems
+        .into_iter()
+        .map(|problem| {
+            let file = problem
+                .file
+                .and_then(|file| {
+                    file.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_default();
+            (file, problem.key)
+        })
+        .collect()
+}
+
+#[test]
+fn all_config_problems_are_reported_with_their_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("base.toml");
+    std::fs::write(
+        &base,
+        r##"
+budgetName = "Budget"
+personalAccessToken = "token"
+showAllRow = true
+
+[categoryGroupWatchList]
+Essentials = "#dfe7f5"
+Fun = "orange"
+"##,
+    )
+    .unwrap();
+    let local = dir.path().join("local.yaml");
+    std::fs::write(
+        &local,
+        r##"
+resolutionDate: 2024-3-13
+outputFormat:
+  csv_output: report.csv
+  visual_output: report.html
+currencyFormat:
+  currencySymbol: "€"
+  symbolFrist: false
+"##,
+    )
+    .unwrap();
+
+    let err = load_layered_config(&[&base, &local]).unwrap_err();
+    insta::assert_snapshot!(
+        err.to_string()
+            .replace(&dir.path().display().to_string(), "<dir>")
+    );
+    assert_eq!(
+        problem_locations(err),
+        vec![
+            ("local.yaml".to_string(), "resolutionDate".to_string()),
+            ("local.yaml".to_string(), "outputFormat".to_string()),
+            (
+                "local.yaml".to_string(),
+                "currencyFormat.symbolFrist".to_string()
+            ),
+            ("base.toml".to_string(), "showAllRow".to_string()),
+            (
+                "base.toml".to_string(),
+                "categoryGroupWatchList.Fun".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn wrongly_typed_values_are_reported_alongside_other_problems() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "resolutionDate": "13/03/2024",
+            "budgets": [{"budgetName": "Joint", "categoryGroupWatchList": {"Bills": 5}}]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            "resolutionDate".to_string(),
+            "budgets[0].categoryGroupWatchList.Bills".to_string()
+        ]
+    );
+}
+
+#[test]
+fn config_built_in_code_is_validated_without_files() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"}
+        }"##,
+    );
+    let mut cfg = load_config(file.path()).unwrap();
+    cfg.source = Default::default();
+    cfg.category_group_watch_list
+        .insert("Fun".to_string(), "#12345".to_string());
+    cfg.output_formats = vec![OutputFormat::VisualFile {
+        visual_output: PathBuf::from("/nonexistent/dir/report.html"),
+    }];
+
+    let err = cfg.validate().unwrap_err();
+    insta::assert_snapshot!(err.to_string());
+}
+
+#[test]
+fn legacy_resolution_date_is_read_with_a_warning() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "resolution_date": "2024-03-13"
+        }"##,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    assert_eq!(
+        cfg.resolution_date,
+        chrono::NaiveDate::from_ymd_opt(2024, 3, 13)
+    );
+    let warnings: Vec<(String, String)> = cfg
+        .warnings()
+        .iter()
+        .map(|warning| (warning.key.clone(), warning.message.clone()))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![(
+            "resolution_date".to_string(),
+            "deprecated, use resolutionDate instead".to_string()
+        )]
+    );
+    assert_eq!(cfg.warnings()[0].file.as_deref(), Some(file.path()));
+}
+
+#[test]
+fn legacy_resolution_date_gives_way_to_the_new_key() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "resolution_date": "2024-03-13"
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        resolution_date: chrono::NaiveDate::from_ymd_opt(2024, 2, 1),
+        ..ConfigOverrides::default()
+    };
+    let cfg = load_config_with_overrides(&[file.path()], &overrides).unwrap();
+    assert_eq!(
+        cfg.resolution_date,
+        chrono::NaiveDate::from_ymd_opt(2024, 2, 1)
+    );
+    assert_eq!(cfg.warnings().len(), 1);
+}
+
+#[test]
+fn command_line_overrides_replace_config_values() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"},
+            "resolutionDate": "2024-03-13",
+            "outputFormat": "polars_print"
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        profile: None,
+        resolution_date: chrono::NaiveDate::from_ymd_opt(2024, 2, 1),
+        period: Some(PeriodKind::Quarter),
+        budget_names: vec!["Joint".into()],
+        output_format: Some(OutputFormat::CsvFile {
+            csv_output: PathBuf::from("report.csv"),
+        }),
+        show_all_rows: Some(true),
+        category_group_watch_list: [("Fun".to_string(), "#f4dccb".to_string())].into(),
+    };
+    let cfg = load_config_with_overrides(&[file.path()], &overrides).unwrap();
+    assert_eq!(
+        cfg.resolution_date,
+        chrono::NaiveDate::from_ymd_opt(2024, 2, 1)
+    );
+    assert_eq!(cfg.period, PeriodKind::Quarter);
+    assert_eq!(cfg.budget_name.as_deref(), Some("Joint"));
+    assert!(matches!(
+        cfg.output_formats.as_slice(),
+        [OutputFormat::CsvFile { .. }]
+    ));
+    assert!(cfg.show_all_rows);
+    let groups: Vec<&String> = cfg.category_group_watch_list.keys().collect();
+    assert_eq!(groups, vec!["Fun"]);
+}
+
+#[test]
+fn budget_override_picks_from_budgets_list() {
+    let file = write_config(
+        r##"{
+            "personalAccessToken": "token",
+            "budgets": [
+                {"budgetName": "Personal", "categoryGroupWatchList": {"Fun": "#f4dccb"}},
+                {"budgetName": "Joint", "categoryGroupWatchList": {"Bills": "#dfe7f5"}}
+            ]
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        budget_names: vec!["Joint".into()],
+        category_group_watch_list: [("Rent".to_string(), "#ffffff".to_string())].into(),
+        ..ConfigOverrides::default()
+    };
+    let budgets = load_config_with_overrides(&[file.path()], &overrides)
+        .unwrap()
+        .budget_configs()
+        .unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].budget_name, "Joint");
+    let groups: Vec<&String> = budgets[0].category_group_watch_list.keys().collect();
+    assert_eq!(groups, vec!["Rent"]);
+
+    let missing = ConfigOverrides {
+        budget_names: vec!["Holiday".into()],
+        ..ConfigOverrides::default()
+    };
+    let err = load_config_with_overrides(&[file.path()], &missing).unwrap_err();
+    assert!(err.to_string().contains("no budget named \"Holiday\""));
+}
+
+#[test]
+fn several_budget_overrides_share_the_watch_list() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"}
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        budget_names: vec!["Personal".into(), "Joint".into()],
+        ..ConfigOverrides::default()
+    };
+    let budgets = load_config_with_overrides(&[file.path()], &overrides)
+        .unwrap()
+        .budget_configs()
+        .unwrap();
+    let names: Vec<&str> = budgets.iter().map(|b| b.budget_name.as_str()).collect();
+    assert_eq!(names, vec!["Personal", "Joint"]);
+    assert!(
+        budgets
+            .iter()
+            .all(|b| b.category_group_watch_list.contains_key("Essentials"))
+    );
+}
+
+#[test]
+fn category_rules_are_kept_for_every_budget() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"},
+            "categoryRules": {
+                "Essentials": {"exclude": ["Rent"], "aliases": {"Groceries": "Food"}},
+                "Fun": {"include": ["Books"], "order": ["Books"]}
+            }
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        budget_names: vec!["Personal".into(), "Joint".into()],
+        ..ConfigOverrides::default()
+    };
+    let budgets = load_config_with_overrides(&[file.path()], &overrides)
+        .unwrap()
+        .budget_configs()
+        .unwrap();
+    for budget in &budgets {
+        let essentials = &budget.category_rules["Essentials"];
+        assert_eq!(essentials.exclude, vec!["Rent"]);
+        assert_eq!(essentials.aliases["Groceries"], "Food");
+        assert_eq!(budget.category_rules["Fun"].include, vec!["Books"]);
+    }
+}
+
+#[test]
+fn category_both_excluded_and_included_is_a_config_problem() {
+    let file = write_config(
+        r##"{
+            "budgets": [{
+                "budgetName": "Budget",
+                "categoryGroupWatchList": {},
+                "categoryRules": {"Fun": {"exclude": ["Books"], "include": ["Books"], "hide": []}}
+            }],
+            "personalAccessToken": "token"
+        }"##,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            "budgets[0].categoryRules.Fun.hide",
+            "budgets[0].categoryRules.Fun.exclude",
+        ]
+    );
+}
+
+#[test]
+fn bad_watch_list_patterns_are_config_problems() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"re:(": "#dfe7f5", "glob:[": "#dfe7f5", "id:g1": "#dfe7f5"},
+            "categoryRules": {"re:[": {}, "glob:Fun*": {}}
+        }"##,
+    );
+    let crustynab::Error::ConfigProblems(problems) = load_config(file.path()).unwrap_err() else {
+        panic!("expected config problems");
+    };
+    let found: Vec<(&str, &str)> = problems
+        .iter()
+        .map(|p| (p.key.as_str(), p.message.split(':').next().unwrap()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("categoryGroupWatchList.glob:[", "invalid glob"),
+            ("categoryGroupWatchList.re:(", "invalid regex"),
+            ("categoryRules.re:[", "invalid regex"),
+        ]
+    );
+}
+
+#[test]
+fn bad_override_values_point_at_the_command_line() {
+    let file = write_config(
+        r##"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "categoryGroupWatchList": {"Essentials": "#dfe7f5"}
+        }"##,
+    );
+    let overrides = ConfigOverrides {
+        category_group_watch_list: [("Fun".to_string(), "orange".to_string())].into(),
+        ..ConfigOverrides::default()
+    };
+    let err = load_config_with_overrides(&[file.path()], &overrides).unwrap_err();
+    insta::assert_snapshot!(err.to_string());
+}
+
+#[test]
+fn output_format_can_list_several_targets() {
+    let file = write_config_as(
+        ".yaml",
+        r##"
+budgetName: Budget
+personalAccessToken: token
+outputFormat:
+  - polars_print
+  - visual_output: report.html
+  - csv_output: report.csv
+"##,
+    );
+    let cfg = load_config(file.path()).unwrap();
+    assert!(matches!(
+        cfg.output_formats.as_slice(),
+        [
+            OutputFormat::Simple(_),
+            OutputFormat::VisualFile { .. },
+            OutputFormat::CsvFile { .. }
+        ]
+    ));
+}
+
+#[test]
+fn output_target_problems_point_at_their_list_entry() {
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "outputFormat": [
+                {"csv_output": "report.csv"},
+                {"csv_output": "a.csv", "visual_output": "a.html"},
+                {"visual_output": "/nonexistent/dir/report.html"},
+                {"csv_output": "report.csv"}
+            ]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(keys, vec!["outputFormat[1]".to_string()]);
+
+    let file = write_config(
+        r#"{
+            "budgetName": "Budget",
+            "personalAccessToken": "token",
+            "outputFormat": [
+                {"csv_output": "report.csv"},
+                {"visual_output": "/nonexistent/dir/report.html"},
+                {"csv_output": "report.csv"}
+            ]
+        }"#,
+    );
+    let err = load_config(file.path()).unwrap_err();
+    let keys: Vec<String> = problem_locations(err)
+        .into_iter()
+        .map(|(_, key)| key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            "outputFormat[1].visual_output".to_string(),
+            "outputFormat[2].csv_output".to_string()
+        ]
+    );
+}
+
+fn profiles_config() -> tempfile::NamedTempFile {
+    write_config_as(
+        ".toml",
+        r##"
+budgetName = "Household"
+personalAccessToken = "token"
+outputFormat = "polars_print"
+
+[categoryGroupWatchList]
+Essentials = "#dfe7f5"
+Fun = "#f4dccb"
+
+[profiles.fun]
+showAllRows = true
+categoryGroupWatchList = { Essentials = "#dfe7f5" }
+outputFormat = { visual_output = "fun.html" }
+
+[profiles.partner]
+budgetName = "Partner"
+
+[profiles.broken.categoryGroupWatchList]
+Fun = "pink"
+"##,
+    )
+}
+
+#[test]
+fn profile_is_merged_over_shared_settings() {
+    let file = profiles_config();
+    let overrides = ConfigOverrides {
+        profile: Some("fun".into()),
+        ..ConfigOverrides::default()
+    };
+    let cfg = load_config_with_overrides(&[file.path()], &overrides).unwrap();
+    assert!(cfg.show_all_rows);
+    assert_eq!(cfg.budget_name.as_deref(), Some("Household"));
+    assert!(matches!(
+        cfg.output_formats.as_slice(),
+        [OutputFormat::VisualFile { .. }]
+    ));
+    let groups: Vec<(&str, &str)> = cfg
+        .category_group_watch_list
+        .iter()
+        .map(|(name, color)| (name.as_str(), color.as_str()))
+        .collect();
+    assert_eq!(groups, vec![("Essentials", "#dfe7f5")]);
+
+    let cfg = load_config(file.path()).unwrap();
+    assert!(!cfg.show_all_rows);
+    assert_eq!(cfg.budget_name.as_deref(), Some("Household"));
+}
+
+#[test]
+fn command_line_overrides_apply_over_the_profile() {
+    let file = profiles_config();
+    let overrides = ConfigOverrides {
+        profile: Some("partner".into()),
+        show_all_rows: Some(true),
+        ..ConfigOverrides::default()
+    };
+    let cfg = load_config_with_overrides(&[file.path()], &overrides).unwrap();
+    assert_eq!(cfg.budget_name.as_deref(), Some("Partner"));
+    assert!(cfg.show_all_rows);
+}
+
+#[test]
+fn profile_problems_point_into_the_profile() {
+    let file = profiles_config();
+    let broken = ConfigOverrides {
+        profile: Some("broken".into()),
+        ..ConfigOverrides::default()
+    };
+    let keys: Vec<String> =
+        problem_locations(load_config_with_overrides(&[file.path()], &broken).unwrap_err())
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+    assert_eq!(
+        keys,
+        vec!["profiles.broken.categoryGroupWatchList.Fun".to_string()]
+    );
+
+    let missing = ConfigOverrides {
+        profile: Some("holiday".into()),
+        ..ConfigOverrides::default()
+    };
+    let err = load_config_with_overrides(&[file.path()], &missing).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("no profile named \"holiday\"; there are broken, fun, partner")
+    );
+}
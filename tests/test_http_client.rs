@@ -0,0 +1,489 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use crustynab::Error;
+use crustynab::money::Milliunits;
+use crustynab::ynab::{
+    AccountType, Category, Frequency, HttpYnabClient, MonthDetail, RateLimit, RetryPolicy, YnabApi,
+    get_month_categories_for,
+};
+
+/// A canned HTTP response from [`StandInServer`].
+struct Reply {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Reply {
+    fn ok(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn error(status: &'static str, detail: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: serde_json::json!({"error": {"id": "x", "name": "error", "detail": detail}})
+                .to_string(),
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Minimal stand-in for the YNAB API: answers every request on a fresh connection with
+/// whatever `respond` returns for the request path and the number of requests seen before
+/// it, after `delay`.
+struct StandInServer {
+    base_path: String,
+    peak_in_flight: Arc<AtomicUsize>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl StandInServer {
+    fn start(delay: Duration, respond: fn(&str) -> String) -> Self {
+        Self::start_scripted(delay, move |path, _| Reply::ok(respond(path)))
+    }
+
+    fn start_scripted(
+        delay: Duration,
+        respond: impl Fn(&str, usize) -> Reply + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_path = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak_in_flight = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let respond = Arc::new(respond);
+
+        let peak = Arc::clone(&peak_in_flight);
+        let seen = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                let seen = Arc::clone(&seen);
+                let respond = Arc::clone(&respond);
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let mut header = String::new();
+                    while reader.read_line(&mut header).unwrap() > 2 {
+                        header.clear();
+                    }
+                    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+                    let index = seen.fetch_add(1, Ordering::SeqCst);
+
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(delay);
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let reply = respond(path, index);
+                    let headers: String = reply
+                        .headers
+                        .iter()
+                        .map(|(name, value)| format!("{name}: {value}\r\n"))
+                        .collect();
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{}",
+                        reply.status,
+                        reply.body.len(),
+                        reply.body
+                    );
+                    stream.write_all(response.as_bytes()).unwrap();
+                });
+            }
+        });
+
+        Self {
+            base_path,
+            peak_in_flight,
+            requests,
+        }
+    }
+
+    fn client(&self) -> HttpYnabClient {
+        HttpYnabClient::new("token")
+            .unwrap()
+            .with_base_path(self.base_path.clone())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(200),
+            })
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+fn category_body(path: &str) -> String {
+    let category_id = path.rsplit('/').next().unwrap();
+    let uuid_suffix = category_id.trim_start_matches('c');
+    serde_json::json!({
+        "data": {
+            "category": {
+                "id": format!("00000000-0000-0000-0000-{uuid_suffix:0>12}"),
+                "category_group_id": "00000000-0000-0000-0000-0000000000aa",
+                "category_group_name": "Essentials",
+                "name": format!("Category {uuid_suffix}"),
+                "hidden": false,
+                "budgeted": 1000,
+                "activity": 0,
+                "balance": 1000,
+                "deleted": false
+            }
+        }
+    })
+    .to_string()
+}
+
+fn budgets_body(_path: &str) -> String {
+    serde_json::json!({
+        "data": {
+            "budgets": [
+                {"id": "00000000-0000-0000-0000-000000000001", "name": "Test Budget"}
+            ]
+        }
+    })
+    .to_string()
+}
+
+#[test]
+fn sync_facade_fetches_budgets() {
+    let server = StandInServer::start(Duration::ZERO, budgets_body);
+    let budgets = server.client().get_budgets().unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].name, "Test Budget");
+}
+
+#[test]
+fn batched_month_categories_run_concurrently_up_to_limit() {
+    let server = StandInServer::start(Duration::from_millis(150), category_body);
+    let client = server.client().with_max_concurrent_requests(3);
+    let ids = ["c1", "c2", "c3", "c4", "c5", "c6", "c7"];
+
+    let categories = client
+        .get_month_categories_by_id("b1", NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(), &ids)
+        .unwrap();
+
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Category 1",
+            "Category 2",
+            "Category 3",
+            "Category 4",
+            "Category 5",
+            "Category 6",
+            "Category 7"
+        ]
+    );
+    assert_eq!(server.peak_in_flight.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn transient_server_errors_are_retried() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |path, index| match index {
+        0 => Reply::error("503 Service Unavailable", "down for maintenance"),
+        1 => Reply::error("502 Bad Gateway", "bad gateway"),
+        _ => Reply::ok(budgets_body(path)),
+    });
+    let budgets = server.client().get_budgets().unwrap();
+    assert_eq!(budgets[0].name, "Test Budget");
+    assert_eq!(server.requests(), 3);
+}
+
+#[test]
+fn transient_errors_give_up_after_max_retries() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |_, _| {
+        Reply::error("500 Internal Server Error", "something broke")
+    });
+    let err = server.client().get_budgets().unwrap_err();
+    assert_eq!(server.requests(), 4);
+    assert!(format!("{err:#}").contains("something broke"));
+}
+
+#[test]
+fn client_errors_are_not_retried() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |_, _| {
+        Reply::error("401 Unauthorized", "Unauthorized")
+    });
+    let err = server.client().get_budgets().unwrap_err();
+    assert_eq!(server.requests(), 1);
+    assert!(matches!(err, Error::Auth(_)));
+    assert_eq!(
+        err.to_string(),
+        "YNAB rejected the access token: Unauthorized"
+    );
+}
+
+#[test]
+fn missing_resources_are_not_found() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |_, _| {
+        Reply::error("404 Not Found", "Resource not found")
+    });
+    let err = server
+        .client()
+        .get_month_category("b1", NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(), "nope")
+        .unwrap_err();
+    assert_eq!(server.requests(), 1);
+    assert!(matches!(err, Error::NotFound(_)));
+}
+
+#[test]
+fn retry_after_is_honoured() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |path, index| match index {
+        0 => Reply::error("429 Too Many Requests", "slow down").header("Retry-After", "0"),
+        _ => Reply::ok(budgets_body(path)),
+    });
+    let client = server.client().with_retry_policy(RetryPolicy {
+        max_retries: 1,
+        initial_backoff: Duration::from_secs(30),
+        max_backoff: Duration::from_secs(60),
+    });
+    let started = std::time::Instant::now();
+    assert!(client.get_budgets().is_ok());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.requests(), 2);
+}
+
+#[test]
+fn exhausted_hourly_quota_is_a_typed_error() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |_, _| {
+        Reply::error("429 Too Many Requests", "Too many requests")
+            .header("X-Rate-Limit", "200/200")
+            .header("Retry-After", "1800")
+    });
+    let client = server.client();
+    let err = client.get_budgets().unwrap_err();
+    assert_eq!(server.requests(), 1);
+
+    let Error::RateLimited(rate_limited) = &err else {
+        panic!("expected a rate limit error, got {err:?}");
+    };
+    assert_eq!(
+        rate_limited.rate_limit,
+        Some(RateLimit {
+            used: 200,
+            limit: 200
+        })
+    );
+    assert_eq!(
+        rate_limited.to_string(),
+        "YNAB rate limit exceeded (200 of 200 requests used in the last hour); try again in 1800 seconds"
+    );
+
+    // Once the quota is known to be spent, later requests fail without reaching the server.
+    assert!(matches!(client.get_budgets(), Err(Error::RateLimited(_))));
+    assert_eq!(server.requests(), 1);
+}
+
+#[test]
+fn rate_limit_header_is_tracked() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |path, _| {
+        Reply::ok(budgets_body(path)).header("X-Rate-Limit", "36/200")
+    });
+    let client = server.client();
+    assert_eq!(client.rate_limit(), None);
+    client.get_budgets().unwrap();
+    assert_eq!(
+        client.rate_limit(),
+        Some(RateLimit {
+            used: 36,
+            limit: 200
+        })
+    );
+}
+
+#[test]
+fn scheduled_transactions_are_fetched() {
+    let server = StandInServer::start(Duration::ZERO, |_| {
+        serde_json::json!({
+            "data": {
+                "scheduled_transactions": [{
+                    "id": "s1",
+                    "date_first": "2024-01-15",
+                    "date_next": "2024-03-15",
+                    "frequency": "everyOtherMonth",
+                    "amount": -80000,
+                    "memo": null,
+                    "flag_color": null,
+                    "account_id": "a1",
+                    "account_name": "Checking",
+                    "payee_id": "p1",
+                    "payee_name": "Landlord",
+                    "category_id": "c1",
+                    "category_name": "Rent",
+                    "transfer_account_id": null,
+                    "subtransactions": [],
+                    "deleted": false
+                }],
+                "server_knowledge": 7
+            }
+        })
+        .to_string()
+    });
+    let scheduled = server.client().get_scheduled_transactions("b1").unwrap();
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled[0].frequency, Frequency::EveryOtherMonth);
+    assert_eq!(
+        scheduled[0].date_next,
+        NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()
+    );
+}
+
+#[test]
+fn accounts_are_fetched() {
+    let server = StandInServer::start(Duration::ZERO, |_| {
+        serde_json::json!({
+            "data": {
+                "accounts": [
+                    {
+                        "id": "a1",
+                        "name": "Mortgage",
+                        "type": "mortgage",
+                        "on_budget": false,
+                        "closed": false,
+                        "note": null,
+                        "balance": -150000000,
+                        "cleared_balance": -150000000,
+                        "uncleared_balance": 0,
+                        "transfer_payee_id": "p1",
+                        "deleted": false
+                    },
+                    {
+                        "id": "a2",
+                        "name": "Pension",
+                        "type": "somethingNew",
+                        "on_budget": false,
+                        "closed": false,
+                        "balance": 2000000,
+                        "cleared_balance": 2000000,
+                        "uncleared_balance": 0,
+                        "deleted": false
+                    }
+                ],
+                "server_knowledge": 3
+            }
+        })
+        .to_string()
+    });
+    let accounts = server.client().get_accounts("b1").unwrap();
+    let types: Vec<AccountType> = accounts.iter().map(|a| a.account_type).collect();
+    assert_eq!(types, vec![AccountType::Mortgage, AccountType::Other]);
+    assert!(accounts[0].account_type.is_liability());
+    assert!(!accounts[1].account_type.is_liability());
+}
+
+#[test]
+fn month_summary_and_categories_come_from_one_month_request() {
+    let server = StandInServer::start(Duration::ZERO, |path| {
+        assert!(path.ends_with("/budgets/b1/months/2024-03-01"), "{path}");
+        serde_json::json!({
+            "data": {
+                "month": {
+                    "month": "2024-03-01",
+                    "note": null,
+                    "income": 3200000,
+                    "budgeted": 2950000,
+                    "activity": -1184250,
+                    "to_be_budgeted": 250000,
+                    "age_of_money": null,
+                    "deleted": false,
+                    "categories": [{"id": "c1", "name": "Groceries", "budgeted": 50000}]
+                }
+            }
+        })
+        .to_string()
+    });
+    let month = server
+        .client()
+        .get_month_detail("b1", NaiveDate::from_ymd_opt(2024, 3, 10).unwrap())
+        .unwrap();
+    assert_eq!(month.summary.to_be_budgeted, Milliunits(250000));
+    assert_eq!(month.summary.activity, Milliunits(-1184250));
+    assert_eq!(month.summary.age_of_money, None);
+    assert_eq!(month.categories[0].budgeted, Milliunits(50000));
+    assert_eq!(server.requests(), 1);
+}
+
+fn watched_category(id: &str) -> Category {
+    serde_json::from_value(serde_json::json!({"id": id, "name": id})).unwrap()
+}
+
+#[test]
+fn categories_missing_from_the_month_are_fetched_on_their_own() {
+    let server = StandInServer::start_scripted(Duration::ZERO, |path, _| {
+        let (_, id) = path.split_once("/categories/").unwrap();
+        Reply::ok(serde_json::json!({"data": {"category": {"id": id, "name": id}}}).to_string())
+    });
+    let month: MonthDetail = serde_json::from_value(serde_json::json!({
+        "month": "2024-03-01",
+        "categories": [{"id": "c1", "name": "c1"}]
+    }))
+    .unwrap();
+    let categories = get_month_categories_for(
+        &server.client(),
+        "b1",
+        &month,
+        &[watched_category("c1"), watched_category("c2")],
+    )
+    .unwrap();
+    let ids: Vec<&str> = categories.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, vec!["c1", "c2"]);
+    assert_eq!(server.requests(), 1);
+}
+
+fn budget_body(path: &str) -> String {
+    let data = if path.contains("/categories") {
+        serde_json::json!({"category_groups": [], "server_knowledge": 1})
+    } else if path.contains("/months/") {
+        serde_json::json!({"month": {"month": "2024-03-01", "categories": []}})
+    } else if path.contains("/scheduled_transactions") {
+        serde_json::json!({"scheduled_transactions": []})
+    } else if path.contains("/transactions") {
+        serde_json::json!({"transactions": [], "server_knowledge": 1})
+    } else {
+        serde_json::json!({"accounts": []})
+    };
+    serde_json::json!({ "data": data }).to_string()
+}
+
+#[test]
+fn budget_data_is_fetched_concurrently_up_to_limit() {
+    let server = StandInServer::start(Duration::from_millis(150), budget_body);
+    let client = server.client().with_max_concurrent_requests(4);
+
+    let started = std::time::Instant::now();
+    let data = client
+        .get_budget_data(
+            "b1",
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(),
+            true,
+        )
+        .unwrap();
+
+    assert!(data.accounts.is_some());
+    assert_eq!(server.requests(), 5);
+    assert_eq!(server.peak_in_flight.load(Ordering::SeqCst), 4);
+    assert!(started.elapsed() < Duration::from_millis(600));
+}
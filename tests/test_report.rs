@@ -1,18 +1,31 @@
 use std::collections::HashSet;
 
 use chrono::NaiveDate;
+use crustynab::calendar_weeks::Period;
+use crustynab::config::CategoryRules;
+use crustynab::money::{CurrencyFormat, Milliunits};
 use crustynab::report;
-use crustynab::ynab::{BudgetSummary, Category, CategoryGroup, SubTransaction, Transaction};
+use crustynab::ynab::{
+    Account, AccountType, BudgetSummary, Category, CategoryGroup, ClearedStatus, Frequency,
+    MonthSummary, ScheduledSubTransaction, ScheduledTransaction, SubTransaction, Transaction,
+};
+use polars::prelude::{IntoLazy, LazyFrame, col};
+
+fn no_upcoming() -> report::TransactionFrame {
+    report::transactions_to_polars(&[]).unwrap()
+}
 
 fn make_budget_summaries() -> Vec<BudgetSummary> {
     vec![
         BudgetSummary {
             id: "b1".into(),
             name: "Budget A".into(),
+            currency_format: None,
         },
         BudgetSummary {
             id: "b2".into(),
             name: "Budget B".into(),
+            currency_format: None,
         },
     ]
 }
@@ -29,21 +42,21 @@ fn make_category_groups() -> Vec<CategoryGroup> {
                     id: "c1".into(),
                     name: "Groceries".into(),
                     category_group_name: Some("Essentials".into()),
-                    budgeted: 50000,
-                    balance: 31500,
+                    budgeted: Milliunits(50000),
+                    balance: Milliunits(31500),
                     goal_cadence: Some(1),
-                    goal_target: Some(60000),
-                    hidden: false,
+                    goal_target: Some(Milliunits(60000)),
+                    ..Default::default()
                 },
                 Category {
                     id: "c2".into(),
                     name: "Rent".into(),
                     category_group_name: Some("Essentials".into()),
-                    budgeted: 100000,
-                    balance: 75000,
-                    goal_cadence: Some(12),
-                    goal_target: Some(120000),
-                    hidden: false,
+                    budgeted: Milliunits(100000),
+                    balance: Milliunits(75000),
+                    goal_cadence: Some(13),
+                    goal_target: Some(Milliunits(120000)),
+                    ..Default::default()
                 },
             ],
         },
@@ -57,21 +70,19 @@ fn make_category_groups() -> Vec<CategoryGroup> {
                     id: "c3".into(),
                     name: "Books".into(),
                     category_group_name: Some("Fun".into()),
-                    budgeted: 10000,
-                    balance: 6000,
+                    budgeted: Milliunits(10000),
+                    balance: Milliunits(6000),
                     goal_cadence: Some(1),
-                    goal_target: None,
-                    hidden: false,
+                    ..Default::default()
                 },
                 Category {
                     id: "c4".into(),
                     name: "Games".into(),
                     category_group_name: Some("Fun".into()),
-                    budgeted: 20000,
-                    balance: 17000,
+                    budgeted: Milliunits(20000),
+                    balance: Milliunits(17000),
                     goal_cadence: Some(1),
-                    goal_target: None,
-                    hidden: false,
+                    ..Default::default()
                 },
             ],
         },
@@ -83,49 +94,82 @@ fn make_transactions() -> Vec<Transaction> {
         Transaction {
             id: "t1".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
-            amount: -12500,
+            amount: Milliunits(-12500),
             payee_name: Some("Market".into()),
             category_name: Some("Groceries".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
         Transaction {
             id: "t4".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 13).unwrap(),
-            amount: -10000,
+            amount: Milliunits(-10000),
             payee_name: Some("Market".into()),
             category_name: Some("Split".into()),
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
             subtransactions: vec![
                 SubTransaction {
-                    amount: -6000,
+                    amount: Milliunits(-6000),
                     payee_name: None,
                     category_name: Some("Groceries".into()),
+                    memo: None,
+                    transfer_account_id: None,
+                    deleted: false,
                 },
                 SubTransaction {
-                    amount: -4000,
+                    amount: Milliunits(-4000),
                     payee_name: None,
                     category_name: Some("Books".into()),
+                    memo: None,
+                    transfer_account_id: None,
+                    deleted: false,
                 },
             ],
+            ..Default::default()
         },
         Transaction {
             id: "t3".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
-            amount: -25000,
+            amount: Milliunits(-25000),
             payee_name: Some("Landlord".into()),
             category_name: Some("Rent".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
         Transaction {
             id: "t2".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
-            amount: -3000,
+            amount: Milliunits(-3000),
             payee_name: Some("Arcade".into()),
             category_name: Some("Games".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
     ]
 }
 
+/// The transaction columns the report itself uses, so snapshots stay readable.
+fn core_columns(frame: LazyFrame) -> LazyFrame {
+    frame.select([
+        col("date"),
+        col("amount"),
+        col("payee_name"),
+        col("category_name"),
+    ])
+}
+
 #[test]
 fn get_budget_id_finds_match() {
     let summaries = make_budget_summaries();
@@ -157,17 +201,166 @@ fn get_categories_to_watch_filters_correctly() {
     let groups = make_category_groups();
     let mut watch_list = indexmap::IndexMap::new();
     watch_list.insert("Essentials".into(), "#fff".into());
-    let cats = report::get_categories_to_watch(&groups, &watch_list);
+    let cats = report::get_categories_to_watch(&groups, &watch_list, &indexmap::IndexMap::new());
     let mut names: Vec<&str> = cats.iter().map(|c| c.name.as_str()).collect();
     names.sort();
     insta::assert_snapshot!(format!("{:?}", names));
 }
 
+fn category_rules() -> indexmap::IndexMap<String, CategoryRules> {
+    let mut rules = indexmap::IndexMap::new();
+    rules.insert(
+        "Essentials".to_string(),
+        CategoryRules {
+            exclude: vec!["Rent".into()],
+            aliases: [("Groceries".to_string(), "Food".to_string())].into(),
+            ..CategoryRules::default()
+        },
+    );
+    rules.insert(
+        "Fun".to_string(),
+        CategoryRules {
+            include: vec!["Games".into(), "Darts".into()],
+            order: vec!["Games".into()],
+            ..CategoryRules::default()
+        },
+    );
+    rules.insert("Travel".to_string(), CategoryRules::default());
+    rules
+}
+
+#[test]
+fn category_rules_exclude_and_include_categories() {
+    let groups = make_category_groups();
+    let mut watch_list = indexmap::IndexMap::new();
+    watch_list.insert("Essentials".into(), "#fff".into());
+    let cats = report::get_categories_to_watch(&groups, &watch_list, &category_rules());
+    let names: Vec<&str> = cats.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Groceries", "Games"]);
+}
+
+#[test]
+fn unknown_rule_categories_are_listed() {
+    let groups = make_category_groups();
+    let unknown = report::get_unknown_rule_categories(&groups, &category_rules());
+    assert_eq!(unknown, vec!["Fun/Darts", "Travel"]);
+}
+
+#[test]
+fn category_rule_keys_match_groups_like_the_watch_list() {
+    let groups = make_category_groups();
+    let rules: indexmap::IndexMap<String, CategoryRules> = category_rules()
+        .into_values()
+        .zip(["essentials", "glob:F*", "re:^Trav"])
+        .map(|(rules, key)| (key.to_string(), rules))
+        .collect();
+    assert_eq!(
+        report::get_unknown_rule_categories(&groups, &rules),
+        vec!["glob:F*/Darts", "re:^Trav"]
+    );
+
+    let resolved = crustynab::watch_list::resolve(&groups, &rules).unwrap();
+    let resolved_keys: Vec<&str> = resolved.keys().map(String::as_str).collect();
+    assert_eq!(resolved_keys, vec!["Essentials", "Fun"]);
+    let mut watch_list = indexmap::IndexMap::new();
+    watch_list.insert("Essentials".into(), "#fff".into());
+    let cats = report::get_categories_to_watch(&groups, &watch_list, &resolved);
+    let names: Vec<&str> = cats.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Groceries", "Games"]);
+}
+
+#[test]
+fn category_rules_rename_and_order_every_table() {
+    let groups = make_category_groups();
+    let all_cats: Vec<Category> = groups.into_iter().flat_map(|g| g.categories).collect();
+    let cf = report::categories_to_polars(&all_cats).unwrap();
+    let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
+    let tf = report::relevant_transactions(
+        report::transactions_to_polars(&make_transactions()).unwrap(),
+        &Period::between(start, end),
+    );
+
+    let cat_names: HashSet<String> = all_cats.iter().map(|c| c.name.clone()).collect();
+    let rules = category_rules();
+    let report = report::build_report_table(cf, tf.clone(), no_upcoming(), &cat_names).unwrap();
+    let report = report::apply_category_rules(report, &rules).unwrap();
+    let section =
+        report::apply_category_rules(report::build_upcoming_table(tf, &cat_names), &rules).unwrap();
+
+    let report_df = report.collect().unwrap();
+    let section_df = section.collect().unwrap();
+    insta::assert_snapshot!(format!("{report_df}\n{section_df}"));
+}
+
+#[test]
+fn category_rules_only_apply_to_their_own_group() {
+    let table = polars::df!(
+        "category_group_name" => ["Essentials", "Essentials", "Fun", "Fun"],
+        "category_name" => ["Misc", "Rent", "Games", "Misc"],
+        "budgeted" => [1i64, 2, 3, 4],
+    )
+    .unwrap()
+    .lazy();
+    let mut rules = indexmap::IndexMap::new();
+    rules.insert(
+        "Essentials".to_string(),
+        CategoryRules {
+            order: vec!["Rent".into()],
+            ..CategoryRules::default()
+        },
+    );
+    rules.insert(
+        "Fun".to_string(),
+        CategoryRules {
+            aliases: [("Misc".to_string(), "Fun money".to_string())].into(),
+            order: vec!["Misc".into()],
+            ..CategoryRules::default()
+        },
+    );
+
+    let df = report::apply_category_rules(table, &rules)
+        .unwrap()
+        .collect()
+        .unwrap();
+    let rows: Vec<(&str, &str, i64)> = df
+        .column("category_group_name")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .zip(
+            df.column("category_name")
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_no_null_iter(),
+        )
+        .zip(
+            df.column("budgeted")
+                .unwrap()
+                .i64()
+                .unwrap()
+                .into_no_null_iter(),
+        )
+        .map(|((group, name), budgeted)| (group, name, budgeted))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("Essentials", "Rent", 2),
+            ("Essentials", "Misc", 1),
+            ("Fun", "Fun money", 4),
+            ("Fun", "Games", 3),
+        ]
+    );
+}
+
 #[test]
 fn transactions_to_polars_expands_splits() {
     let transactions = make_transactions();
     let tf = report::transactions_to_polars(&transactions).unwrap();
-    let df = tf.0.collect().unwrap();
+    let df = core_columns(tf.0).collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
 
@@ -176,18 +369,96 @@ fn categories_to_polars_converts_milliunits() {
     let groups = make_category_groups();
     let cats: Vec<Category> = groups.into_iter().flat_map(|g| g.categories).collect();
     let cf = report::categories_to_polars(&cats).unwrap();
-    let df = cf.0.collect().unwrap();
+    let df =
+        cf.0.select([
+            col("category_name"),
+            col("category_group_name"),
+            col("budgeted"),
+            col("balance"),
+            col("goal_cadence"),
+        ])
+        .collect()
+        .unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
 
+#[test]
+fn categories_to_polars_carries_goal_metadata() {
+    let categories = vec![
+        Category {
+            id: "c1".into(),
+            name: "Holiday".into(),
+            category_group_name: Some("Savings".into()),
+            budgeted: Milliunits(50000),
+            balance: Milliunits(300000),
+            goal_type: Some("TBD".into()),
+            goal_cadence: Some(0),
+            goal_target: Some(Milliunits(1200000)),
+            goal_target_month: NaiveDate::from_ymd_opt(2024, 12, 1),
+            goal_percentage_complete: Some(25),
+            goal_under_funded: Some(Milliunits(50000)),
+            goal_overall_left: Some(Milliunits(900000)),
+            goal_months_to_budget: Some(10),
+            ..Default::default()
+        },
+        Category {
+            id: "c2".into(),
+            name: "Snacks".into(),
+            category_group_name: Some("Fun".into()),
+            budgeted: Milliunits(10000),
+            balance: Milliunits(4000),
+            ..Default::default()
+        },
+    ];
+    let mut df = report::categories_to_polars(&categories)
+        .unwrap()
+        .0
+        .collect()
+        .unwrap();
+    insta::assert_snapshot!(report::write_csv_string(&mut df).unwrap());
+}
+
+#[test]
+fn goal_per_year_follows_the_goal_cadence() {
+    let goal = |name: &str, cadence: i32, frequency: Option<i32>, target: i64| Category {
+        id: name.to_lowercase(),
+        name: name.into(),
+        category_group_name: Some("Bills".into()),
+        goal_cadence: Some(cadence),
+        goal_cadence_frequency: frequency,
+        goal_target: Some(Milliunits(target)),
+        ..Default::default()
+    };
+    let categories = vec![
+        goal("Phone", 1, Some(1), 30000),
+        goal("Cleaner", 1, Some(2), 60000),
+        goal("Lunch", 2, Some(1), 10000),
+        goal("Water", 4, None, 90000),
+        goal("Insurance", 13, Some(1), 480000),
+        goal("Passport", 14, None, 100000),
+        goal("Wedding", 0, None, 2000000),
+    ];
+    let mut df = report::categories_to_polars(&categories)
+        .unwrap()
+        .0
+        .select([
+            col("category_name"),
+            col("goal_cadence"),
+            col("goal_per_year"),
+        ])
+        .collect()
+        .unwrap();
+    insta::assert_snapshot!(report::write_csv_string(&mut df).unwrap());
+}
+
 #[test]
 fn relevant_transactions_filters_date_range() {
     let transactions = make_transactions();
     let tf = report::transactions_to_polars(&transactions).unwrap();
     let start = NaiveDate::from_ymd_opt(2024, 3, 12).unwrap();
     let end = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
-    let filtered = report::relevant_transactions(tf, start, end);
-    let df = filtered.0.collect().unwrap();
+    let filtered = report::relevant_transactions(tf, &Period::between(start, end));
+    let df = core_columns(filtered.0).collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
 
@@ -201,10 +472,10 @@ fn build_report_table_sums_spent() {
     let tf = report::transactions_to_polars(&transactions).unwrap();
     let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
     let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
-    let tf = report::relevant_transactions(tf, start, end);
+    let tf = report::relevant_transactions(tf, &Period::between(start, end));
 
     let cat_names: HashSet<String> = all_cats.iter().map(|c| c.name.clone()).collect();
-    let report = report::build_report_table(cf, tf, &cat_names).unwrap();
+    let report = report::build_report_table(cf, tf, no_upcoming(), &cat_names).unwrap();
     let df = report.collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
@@ -219,10 +490,10 @@ fn category_group_totals_match_rows() {
     let tf = report::transactions_to_polars(&transactions).unwrap();
     let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
     let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
-    let tf = report::relevant_transactions(tf, start, end);
+    let tf = report::relevant_transactions(tf, &Period::between(start, end));
 
     let cat_names: HashSet<String> = all_cats.iter().map(|c| c.name.clone()).collect();
-    let report = report::build_report_table(cf, tf, &cat_names).unwrap();
+    let report = report::build_report_table(cf, tf, no_upcoming(), &cat_names).unwrap();
     let totals = report::build_category_group_totals_table(report).unwrap();
     let df = totals.collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
@@ -235,21 +506,21 @@ fn category_group_totals_include_balance_without_spending() {
             id: "c1".into(),
             name: "Groceries".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 50000,
-            balance: 30000,
+            budgeted: Milliunits(50000),
+            balance: Milliunits(30000),
             goal_cadence: Some(1),
-            goal_target: Some(60000),
-            hidden: false,
+            goal_target: Some(Milliunits(60000)),
+            ..Default::default()
         },
         Category {
             id: "c2".into(),
             name: "Savings".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 20000,
-            balance: 90000,
+            budgeted: Milliunits(20000),
+            balance: Milliunits(90000),
             goal_cadence: Some(1),
-            goal_target: Some(60000),
-            hidden: false,
+            goal_target: Some(Milliunits(60000)),
+            ..Default::default()
         },
     ];
     let cf = report::categories_to_polars(&categories).unwrap();
@@ -257,18 +528,22 @@ fn category_group_totals_include_balance_without_spending() {
     let transactions = vec![Transaction {
         id: "t1".into(),
         date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
-        amount: -12500,
+        amount: Milliunits(-12500),
         payee_name: Some("Market".into()),
         category_name: Some("Groceries".into()),
-        subtransactions: vec![],
+        cleared: ClearedStatus::Cleared,
+        approved: true,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
+        ..Default::default()
     }];
     let tf = report::transactions_to_polars(&transactions).unwrap();
     let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
     let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
-    let tf = report::relevant_transactions(tf, start, end);
+    let tf = report::relevant_transactions(tf, &Period::between(start, end));
 
     let cat_names: HashSet<String> = categories.iter().map(|c| c.name.clone()).collect();
-    let report = report::build_report_table(cf, tf, &cat_names).unwrap();
+    let report = report::build_report_table(cf, tf, no_upcoming(), &cat_names).unwrap();
     let totals = report::build_category_group_totals_table(report).unwrap();
     let df = totals.collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
@@ -281,21 +556,21 @@ fn split_transactions_are_counted_per_category() {
             id: "c1".into(),
             name: "Groceries".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 50000,
-            balance: 30000,
+            budgeted: Milliunits(50000),
+            balance: Milliunits(30000),
             goal_cadence: Some(1),
-            goal_target: Some(60000),
-            hidden: false,
+            goal_target: Some(Milliunits(60000)),
+            ..Default::default()
         },
         Category {
             id: "c2".into(),
             name: "Savings".into(),
             category_group_name: Some("Essentials".into()),
-            budgeted: 20000,
-            balance: 90000,
+            budgeted: Milliunits(20000),
+            balance: Milliunits(90000),
             goal_cadence: Some(1),
-            goal_target: Some(60000),
-            hidden: false,
+            goal_target: Some(Milliunits(60000)),
+            ..Default::default()
         },
     ];
     let cf = report::categories_to_polars(&categories).unwrap();
@@ -303,29 +578,40 @@ fn split_transactions_are_counted_per_category() {
     let transactions = vec![Transaction {
         id: "t2".into(),
         date: NaiveDate::from_ymd_opt(2024, 3, 11).unwrap(),
-        amount: -20000,
+        amount: Milliunits(-20000),
         payee_name: Some("Market".into()),
         category_name: Some("Split".into()),
+        cleared: ClearedStatus::Cleared,
+        approved: true,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
         subtransactions: vec![
             SubTransaction {
-                amount: -12500,
+                amount: Milliunits(-12500),
                 payee_name: None,
                 category_name: Some("Groceries".into()),
+                memo: None,
+                transfer_account_id: None,
+                deleted: false,
             },
             SubTransaction {
-                amount: -7500,
+                amount: Milliunits(-7500),
                 payee_name: None,
                 category_name: Some("Savings".into()),
+                memo: None,
+                transfer_account_id: None,
+                deleted: false,
             },
         ],
+        ..Default::default()
     }];
     let tf = report::transactions_to_polars(&transactions).unwrap();
     let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
     let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
-    let tf = report::relevant_transactions(tf, start, end);
+    let tf = report::relevant_transactions(tf, &Period::between(start, end));
 
     let cat_names: HashSet<String> = categories.iter().map(|c| c.name.clone()).collect();
-    let report = report::build_report_table(cf, tf, &cat_names).unwrap();
+    let report = report::build_report_table(cf, tf, no_upcoming(), &cat_names).unwrap();
     let df = report.collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
@@ -336,21 +622,300 @@ fn transactions_with_no_category_are_filtered() {
         Transaction {
             id: "t1".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
-            amount: -5000,
+            amount: Milliunits(-5000),
             payee_name: Some("Shop".into()),
-            category_name: None,
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
         Transaction {
             id: "t2".into(),
             date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
-            amount: -3000,
+            amount: Milliunits(-3000),
             payee_name: Some("Store".into()),
             category_name: Some("Groceries".into()),
-            subtransactions: vec![],
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
         },
     ];
     let tf = report::transactions_to_polars(&transactions).unwrap();
-    let df = tf.0.collect().unwrap();
+    let df = core_columns(tf.0).collect().unwrap();
     insta::assert_snapshot!(format!("{df}"));
 }
+
+#[test]
+fn group_totals_reconcile_exactly_in_milliunits() {
+    let categories = vec![Category {
+        id: "c1".into(),
+        name: "Coffee".into(),
+        category_group_name: Some("Fun".into()),
+        budgeted: Milliunits(30000),
+        balance: Milliunits(27000),
+        goal_cadence: Some(1),
+        ..Default::default()
+    }];
+    let transactions: Vec<Transaction> = (0..30)
+        .map(|i| Transaction {
+            id: format!("t{i}"),
+            date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
+            amount: Milliunits(-100),
+            payee_name: Some("Cafe".into()),
+            category_name: Some("Coffee".into()),
+            cleared: ClearedStatus::Cleared,
+            approved: true,
+            account_id: "a1".into(),
+            account_name: "Checking".into(),
+            ..Default::default()
+        })
+        .collect();
+
+    let cf = report::categories_to_polars(&categories).unwrap();
+    let tf = report::transactions_to_polars(&transactions).unwrap();
+    let cat_names: HashSet<String> = categories.iter().map(|c| c.name.clone()).collect();
+    let report = report::build_report_table(cf, tf, no_upcoming(), &cat_names).unwrap();
+    let totals = report::build_category_group_totals_table(report).unwrap();
+
+    let exact = totals.clone().collect().unwrap();
+    let spent = exact.column("spent").unwrap().i64().unwrap();
+    assert_eq!(spent.get(0), Some(-3000));
+
+    let display = report::to_display_units(totals, &CurrencyFormat::default())
+        .unwrap()
+        .collect()
+        .unwrap();
+    let spent = display.column("spent").unwrap().f64().unwrap();
+    assert_eq!(spent.get(1), Some(-3.0));
+}
+
+#[test]
+fn transactions_to_polars_carries_transaction_details() {
+    let transactions = vec![Transaction {
+        id: "t1".into(),
+        date: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
+        amount: Milliunits(-30000),
+        memo: Some("weekly shop".into()),
+        cleared: ClearedStatus::Reconciled,
+        flag_color: Some("red".into()),
+        account_id: "a2".into(),
+        account_name: "Credit Card".into(),
+        payee_name: Some("Market".into()),
+        category_name: Some("Split".into()),
+        import_id: Some("YNAB:-30000:2024-03-12:1".into()),
+        subtransactions: vec![
+            SubTransaction {
+                amount: Milliunits(-20000),
+                memo: None,
+                payee_name: None,
+                category_name: Some("Groceries".into()),
+                transfer_account_id: None,
+                deleted: false,
+            },
+            SubTransaction {
+                amount: Milliunits(-10000),
+                memo: Some("to savings".into()),
+                payee_name: Some("Transfer : Savings".into()),
+                category_name: Some("Savings".into()),
+                transfer_account_id: Some("a3".into()),
+                deleted: false,
+            },
+            SubTransaction {
+                amount: Milliunits(-5000),
+                memo: None,
+                payee_name: None,
+                category_name: Some("Removed".into()),
+                transfer_account_id: None,
+                deleted: true,
+            },
+        ],
+        ..Default::default()
+    }];
+    let mut df = report::transactions_to_polars(&transactions)
+        .unwrap()
+        .0
+        .collect()
+        .unwrap();
+    insta::assert_snapshot!(report::write_csv_string(&mut df).unwrap());
+}
+
+fn scheduled(
+    id: &str,
+    first: NaiveDate,
+    frequency: Frequency,
+    amount: i64,
+) -> ScheduledTransaction {
+    ScheduledTransaction {
+        id: id.into(),
+        date_first: first,
+        date_next: first,
+        frequency,
+        amount: Milliunits(amount),
+        memo: None,
+        flag_color: None,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
+        payee_name: Some("Landlord".into()),
+        category_name: Some("Rent".into()),
+        transfer_account_id: None,
+        subtransactions: vec![],
+        deleted: false,
+    }
+}
+
+#[test]
+fn scheduled_transactions_fill_upcoming_column_and_section() {
+    let groups = make_category_groups();
+    let all_cats: Vec<Category> = groups.iter().flat_map(|g| g.categories.clone()).collect();
+    let cf = report::categories_to_polars(&all_cats).unwrap();
+    let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
+    let tf = report::relevant_transactions(
+        report::transactions_to_polars(&make_transactions()).unwrap(),
+        &Period::between(start, end),
+    );
+
+    let schedules = vec![
+        scheduled(
+            "s1",
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            Frequency::Monthly,
+            -80000,
+        ),
+        ScheduledTransaction {
+            payee_name: Some("Market".into()),
+            category_name: Some("Groceries".into()),
+            ..scheduled(
+                "s2",
+                NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                Frequency::Weekly,
+                -7000,
+            )
+        },
+        ScheduledTransaction {
+            category_name: Some("Unwatched".into()),
+            ..scheduled(
+                "s3",
+                NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+                Frequency::Never,
+                -1000,
+            )
+        },
+        ScheduledTransaction {
+            payee_name: Some("Corner Shop".into()),
+            category_name: Some("Split".into()),
+            subtransactions: vec![
+                ScheduledSubTransaction {
+                    amount: Milliunits(-3000),
+                    memo: Some("food".into()),
+                    category_id: Some("c1".into()),
+                    transfer_account_id: None,
+                    deleted: false,
+                },
+                ScheduledSubTransaction {
+                    amount: Milliunits(-2000),
+                    memo: None,
+                    category_id: Some("c2".into()),
+                    transfer_account_id: None,
+                    deleted: false,
+                },
+            ],
+            ..scheduled(
+                "s4",
+                NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
+                Frequency::Never,
+                -5000,
+            )
+        },
+    ];
+    let upcoming =
+        report::upcoming_transactions_to_polars(&schedules, &groups, start, end).unwrap();
+
+    let cat_names: HashSet<String> = all_cats.iter().map(|c| c.name.clone()).collect();
+    let report = report::build_report_table(cf, tf, upcoming.clone(), &cat_names).unwrap();
+    let section = report::build_upcoming_table(upcoming, &cat_names);
+
+    let report_df = report.collect().unwrap();
+    let section_df = section.collect().unwrap();
+    insta::assert_snapshot!(format!("{report_df}\n{section_df}"));
+}
+
+fn account(name: &str, account_type: AccountType, balance: i64) -> Account {
+    Account {
+        id: name.to_lowercase(),
+        name: name.into(),
+        account_type,
+        on_budget: true,
+        closed: false,
+        balance: Milliunits(balance),
+        cleared_balance: Milliunits(balance),
+        uncleared_balance: Milliunits::ZERO,
+        deleted: false,
+    }
+}
+
+#[test]
+fn net_worth_splits_assets_from_liabilities() {
+    let accounts = vec![
+        account("Checking", AccountType::Checking, 1_250_000),
+        account("Savings", AccountType::Savings, 4_000_000),
+        account("Visa", AccountType::CreditCard, -310_000),
+        Account {
+            on_budget: false,
+            ..account("Mortgage", AccountType::Mortgage, -150_000_000)
+        },
+        Account {
+            deleted: true,
+            ..account("Old", AccountType::Cash, 99_000)
+        },
+    ];
+    let frame = report::accounts_to_polars(&accounts).unwrap();
+    let mut accounts_df = frame.0.clone().collect().unwrap();
+
+    let start = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    let end = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
+    let transactions = vec![
+        Transaction {
+            category_name: None,
+            transfer_account_id: Some("visa".into()),
+            ..make_transactions()[0].clone()
+        },
+        make_transactions()[1].clone(),
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2024, 3, 17).unwrap(),
+            ..make_transactions()[2].clone()
+        },
+    ];
+    let mut net_worth = report::build_net_worth_table(frame, &transactions, start, end)
+        .collect()
+        .unwrap();
+
+    insta::assert_snapshot!(format!(
+        "{}\n{}",
+        report::write_csv_string(&mut accounts_df).unwrap(),
+        report::write_csv_string(&mut net_worth).unwrap()
+    ));
+}
+
+#[test]
+fn month_summary_table_is_one_row_in_display_units() {
+    let summary = MonthSummary {
+        month: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        income: Milliunits(3200000),
+        budgeted: Milliunits(2950000),
+        activity: Milliunits(-1184250),
+        to_be_budgeted: Milliunits(-12345),
+        age_of_money: None,
+    };
+    let table = report::build_month_summary_table(&summary).unwrap();
+    let mut df = report::to_display_units(table, &CurrencyFormat::default())
+        .unwrap()
+        .collect()
+        .unwrap();
+
+    assert_eq!(df.height(), 1);
+    insta::assert_snapshot!(report::write_csv_string(&mut df).unwrap());
+}
@@ -1,8 +1,10 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use chrono::{Duration, NaiveDate};
+use crustynab::calendar_weeks::Period;
+use crustynab::money::Milliunits;
 use crustynab::report::{self, CategoryFrame, TransactionFrame};
-use crustynab::ynab::{BudgetSummary, CategoryGroup, SubTransaction, Transaction};
+use crustynab::ynab::{BudgetSummary, CategoryGroup, ClearedStatus, SubTransaction, Transaction};
 use polars::prelude::*;
 use proptest::prelude::*;
 use proptest::string::string_regex;
@@ -11,8 +13,8 @@ use proptest::string::string_regex;
 struct CategoryRow {
     category_name: String,
     category_group_name: String,
-    budgeted: f64,
-    balance: f64,
+    budgeted: i64,
+    balance: i64,
     goal_cadence: String,
 }
 
@@ -55,9 +57,10 @@ fn category_frame(rows: &[CategoryRow]) -> CategoryFrame {
         .iter()
         .map(|row| row.category_group_name.as_str())
         .collect();
-    let budgeted: Vec<f64> = rows.iter().map(|row| row.budgeted).collect();
-    let balance: Vec<f64> = rows.iter().map(|row| row.balance).collect();
+    let budgeted: Vec<i64> = rows.iter().map(|row| row.budgeted).collect();
+    let balance: Vec<i64> = rows.iter().map(|row| row.balance).collect();
     let goal_cadence: Vec<&str> = rows.iter().map(|row| row.goal_cadence.as_str()).collect();
+    let goal_per_year: Vec<Option<i64>> = vec![None; rows.len()];
 
     let df = DataFrame::new(vec![
         Column::new("category_name".into(), &category_names),
@@ -65,6 +68,7 @@ fn category_frame(rows: &[CategoryRow]) -> CategoryFrame {
         Column::new("budgeted".into(), &budgeted),
         Column::new("balance".into(), &balance),
         Column::new("goal_cadence".into(), &goal_cadence),
+        Column::new("goal_per_year".into(), &goal_per_year),
     ])
     .expect("category frame");
 
@@ -85,10 +89,7 @@ fn transaction_frame(rows: &[TxRow]) -> TransactionFrame {
         .iter()
         .map(|row| date_to_polars_days(row.date))
         .collect();
-    let amounts: Vec<f64> = rows
-        .iter()
-        .map(|row| row.amount_milli as f64 / 1000.0)
-        .collect();
+    let amounts: Vec<i64> = rows.iter().map(|row| row.amount_milli).collect();
     let payees: Vec<Option<&str>> = rows.iter().map(|row| row.payee_name.as_deref()).collect();
     let category_names: Vec<&str> = rows.iter().map(|row| row.category_name.as_str()).collect();
 
@@ -107,18 +108,18 @@ fn transaction_frame(rows: &[TxRow]) -> TransactionFrame {
     TransactionFrame(df.lazy())
 }
 
-fn report_spent_map(df: &DataFrame) -> HashMap<String, f64> {
+fn report_amount_map(df: &DataFrame, column: &str) -> HashMap<String, i64> {
     let categories = df
         .column("category_name")
         .expect("category_name")
         .str()
         .expect("category_name str");
-    let spent = df.column("spent").expect("spent").f64().expect("spent f64");
+    let amounts = df.column(column).expect(column).i64().expect("amount i64");
 
     let mut map = HashMap::new();
     for idx in 0..df.height() {
         let category = categories.get(idx).expect("category").to_string();
-        map.insert(category, spent.get(idx).expect("spent value"));
+        map.insert(category, amounts.get(idx).expect("amount value"));
     }
     map
 }
@@ -133,8 +134,8 @@ fn transaction_multiset(df: &DataFrame) -> BTreeMap<String, usize> {
     let amounts = df
         .column("amount")
         .expect("amount")
-        .f64()
-        .expect("amount f64");
+        .i64()
+        .expect("amount i64");
     let payees = df
         .column("payee_name")
         .expect("payee_name")
@@ -149,7 +150,7 @@ fn transaction_multiset(df: &DataFrame) -> BTreeMap<String, usize> {
     let mut counts = BTreeMap::new();
     for idx in 0..df.height() {
         let day = polars_days_to_date(date_days.get(idx).expect("day value"));
-        let milli = (amounts.get(idx).expect("amount value") * 1000.0).round() as i64;
+        let milli = amounts.get(idx).expect("amount value");
         let payee = payees.get(idx).unwrap_or("<none>");
         let category = categories.get(idx).expect("category value");
         let key = format!("{day}|{milli}|{payee}|{category}");
@@ -159,7 +160,7 @@ fn transaction_multiset(df: &DataFrame) -> BTreeMap<String, usize> {
     counts
 }
 
-fn report_totals_map(df: &DataFrame) -> HashMap<String, (f64, f64, f64)> {
+fn report_totals_map(df: &DataFrame) -> HashMap<String, (i64, i64, i64)> {
     let groups = df
         .column("category_group_name")
         .expect("category_group_name")
@@ -168,14 +169,14 @@ fn report_totals_map(df: &DataFrame) -> HashMap<String, (f64, f64, f64)> {
     let budgeted = df
         .column("budgeted")
         .expect("budgeted")
-        .f64()
-        .expect("budgeted f64");
-    let spent = df.column("spent").expect("spent").f64().expect("spent f64");
+        .i64()
+        .expect("budgeted i64");
+    let spent = df.column("spent").expect("spent").i64().expect("spent i64");
     let balance = df
         .column("balance")
         .expect("balance")
-        .f64()
-        .expect("balance f64");
+        .i64()
+        .expect("balance i64");
 
     let mut map = HashMap::new();
     for idx in 0..df.height() {
@@ -192,10 +193,6 @@ fn report_totals_map(df: &DataFrame) -> HashMap<String, (f64, f64, f64)> {
     map
 }
 
-fn close(a: f64, b: f64) -> bool {
-    (a - b).abs() < 1e-6
-}
-
 fn category_rows_strategy() -> impl Strategy<Value = Vec<CategoryRow>> {
     (
         prop::collection::vec(short_text_strategy(), 1..=8),
@@ -223,8 +220,8 @@ fn category_rows_strategy() -> impl Strategy<Value = Vec<CategoryRow>> {
                     .map(|(idx, category_name)| CategoryRow {
                         category_name,
                         category_group_name: groups[group_indexes[idx]].clone(),
-                        budgeted: budgeted[idx] as f64 / 1000.0,
-                        balance: balance[idx] as f64 / 1000.0,
+                        budgeted: budgeted[idx],
+                        balance: balance[idx],
                         goal_cadence: if monthly[idx] {
                             "monthly".to_string()
                         } else {
@@ -312,6 +309,7 @@ fn budget_summaries_strategy() -> impl Strategy<Value = Vec<BudgetSummary>> {
             .map(|(idx, name)| BudgetSummary {
                 id: format!("budget-{idx}"),
                 name,
+                currency_format: None,
             })
             .collect()
     })
@@ -334,9 +332,12 @@ fn subtransaction_strategy() -> impl Strategy<Value = SubTransaction> {
         prop::option::of(short_text_strategy()),
     )
         .prop_map(|(amount, payee_name, category_name)| SubTransaction {
-            amount,
+            amount: Milliunits(amount),
             payee_name,
             category_name,
+            memo: None,
+            transfer_account_id: None,
+            deleted: false,
         })
 }
 
@@ -360,10 +361,15 @@ fn transaction_strategy() -> impl Strategy<Value = Transaction> {
                 Transaction {
                     id,
                     date,
-                    amount,
+                    amount: Milliunits(amount),
                     payee_name,
                     category_name,
+                    cleared: ClearedStatus::Cleared,
+                    approved: true,
+                    account_id: "a1".into(),
+                    account_name: "Checking".into(),
                     subtransactions,
+                    ..Default::default()
                 }
             },
         )
@@ -435,26 +441,29 @@ proptest! {
 
         let categories_frame = category_frame(&categories);
         let transactions_frame = transaction_frame(&transactions);
+        let upcoming_frame = transaction_frame(&transactions);
 
-        let report_df = report::build_report_table(categories_frame, transactions_frame, &category_names)
+        let report_df = report::build_report_table(categories_frame, transactions_frame, upcoming_frame, &category_names)
             .expect("build_report_table")
             .collect()
             .expect("collect report");
 
-        let mut expected = HashMap::<String, f64>::new();
+        let mut expected = HashMap::<String, i64>::new();
         for tx in transactions {
             if category_names.contains(&tx.category_name) {
-                *expected.entry(tx.category_name.clone()).or_insert(0.0) += tx.amount_milli as f64 / 1000.0;
+                *expected.entry(tx.category_name.clone()).or_insert(0) += tx.amount_milli;
             }
         }
 
-        let actual = report_spent_map(&report_df);
+        let actual = report_amount_map(&report_df, "spent");
+        let actual_upcoming = report_amount_map(&report_df, "upcoming");
         prop_assert_eq!(actual.len(), category_names.len());
 
         for category in category_names {
-            let actual_spent = actual.get(&category).copied().unwrap_or(0.0);
-            let expected_spent = expected.get(&category).copied().unwrap_or(0.0);
-            prop_assert!(close(actual_spent, expected_spent));
+            let actual_spent = actual.get(&category).copied().unwrap_or(0);
+            let expected_spent = expected.get(&category).copied().unwrap_or(0);
+            prop_assert_eq!(actual_spent, expected_spent);
+            prop_assert_eq!(actual_upcoming.get(&category).copied(), Some(expected_spent));
         }
     }
 
@@ -466,7 +475,7 @@ proptest! {
     ) {
         let (start, end) = if start <= end { (start, end) } else { (end, start) };
         let frame = transaction_frame(&rows);
-        let filtered_df = report::relevant_transactions(frame, start, end)
+        let filtered_df = report::relevant_transactions(frame, &Period::between(start, end))
             .0
             .collect()
             .expect("collect filtered");
@@ -497,6 +506,7 @@ proptest! {
         let report_table = report::build_report_table(
             category_frame(&categories),
             transaction_frame(&transactions),
+            transaction_frame(&[]),
             &category_names,
         )
         .expect("build_report_table");
@@ -512,14 +522,14 @@ proptest! {
             .expect("category_group_name")
             .str()
             .expect("category_group_name str");
-        let budgeted = report_df.column("budgeted").expect("budgeted").f64().expect("budgeted f64");
-        let spent = report_df.column("spent").expect("spent").f64().expect("spent f64");
-        let balance = report_df.column("balance").expect("balance").f64().expect("balance f64");
+        let budgeted = report_df.column("budgeted").expect("budgeted").i64().expect("budgeted i64");
+        let spent = report_df.column("spent").expect("spent").i64().expect("spent i64");
+        let balance = report_df.column("balance").expect("balance").i64().expect("balance i64");
 
-        let mut expected = HashMap::<String, (f64, f64, f64)>::new();
+        let mut expected = HashMap::<String, (i64, i64, i64)>::new();
         for idx in 0..report_df.height() {
             let group = groups.get(idx).expect("group value").to_string();
-            let entry = expected.entry(group).or_insert((0.0, 0.0, 0.0));
+            let entry = expected.entry(group).or_insert((0, 0, 0));
             entry.0 += budgeted.get(idx).expect("budgeted value");
             entry.1 += spent.get(idx).expect("spent value");
             entry.2 += balance.get(idx).expect("balance value");
@@ -532,9 +542,9 @@ proptest! {
                 .get(group)
                 .copied()
                 .expect("group exists in totals");
-            prop_assert!(close(act_budgeted, *exp_budgeted));
-            prop_assert!(close(act_spent, *exp_spent));
-            prop_assert!(close(act_balance, *exp_balance));
+            prop_assert_eq!(act_budgeted, *exp_budgeted);
+            prop_assert_eq!(act_spent, *exp_spent);
+            prop_assert_eq!(act_balance, *exp_balance);
         }
 
         let (total_budgeted, total_spent, total_balance) = actual
@@ -542,13 +552,13 @@ proptest! {
             .copied()
             .expect("overall total exists");
 
-        let expected_budgeted: f64 = expected.values().map(|values| values.0).sum();
-        let expected_spent: f64 = expected.values().map(|values| values.1).sum();
-        let expected_balance: f64 = expected.values().map(|values| values.2).sum();
+        let expected_budgeted: i64 = expected.values().map(|values| values.0).sum();
+        let expected_spent: i64 = expected.values().map(|values| values.1).sum();
+        let expected_balance: i64 = expected.values().map(|values| values.2).sum();
 
-        prop_assert!(close(total_budgeted, expected_budgeted));
-        prop_assert!(close(total_spent, expected_spent));
-        prop_assert!(close(total_balance, expected_balance));
+        prop_assert_eq!(total_budgeted, expected_budgeted);
+        prop_assert_eq!(total_spent, expected_spent);
+        prop_assert_eq!(total_balance, expected_balance);
     }
 
     #[test]
@@ -568,7 +578,7 @@ proptest! {
                         expected_rows.push(format!(
                             "{}|{}|{}|{}",
                             transaction.date,
-                            sub.amount,
+                            sub.amount.0,
                             payee,
                             category_name,
                         ));
@@ -579,7 +589,7 @@ proptest! {
                 expected_rows.push(format!(
                     "{}|{}|{}|{}",
                     transaction.date,
-                    transaction.amount,
+                    transaction.amount.0,
                     payee,
                     category_name,
                 ));
@@ -598,7 +608,7 @@ proptest! {
             .cast(&DataType::Int32)
             .expect("cast date");
         let actual_days = actual_date_col.i32().expect("date i32");
-        let actual_amounts = df.column("amount").expect("amount").f64().expect("amount f64");
+        let actual_amounts = df.column("amount").expect("amount").i64().expect("amount i64");
         let actual_payees = df
             .column("payee_name")
             .expect("payee_name")
@@ -613,7 +623,7 @@ proptest! {
         let mut actual_rows = Vec::<String>::new();
         for idx in 0..df.height() {
             let day = polars_days_to_date(actual_days.get(idx).expect("day"));
-            let amount_milli = (actual_amounts.get(idx).expect("amount") * 1000.0).round() as i64;
+            let amount_milli = actual_amounts.get(idx).expect("amount");
             let payee = actual_payees.get(idx).unwrap_or("<none>");
             let category = actual_categories.get(idx).expect("category");
             actual_rows.push(format!("{day}|{amount_milli}|{payee}|{category}"));
@@ -0,0 +1,147 @@
+use chrono::NaiveDate;
+use crustynab::money::Milliunits;
+use crustynab::ynab::{Frequency, ScheduledTransaction};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn schedule(first: NaiveDate, next: NaiveDate, frequency: Frequency) -> ScheduledTransaction {
+    ScheduledTransaction {
+        id: "s1".into(),
+        date_first: first,
+        date_next: next,
+        frequency,
+        amount: Milliunits(-50000),
+        memo: None,
+        flag_color: None,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
+        payee_name: Some("Landlord".into()),
+        category_name: Some("Rent".into()),
+        transfer_account_id: None,
+        subtransactions: vec![],
+        deleted: false,
+    }
+}
+
+#[test]
+fn weekly_schedules_repeat_every_seven_days() {
+    let s = schedule(date(2024, 1, 1), date(2024, 3, 4), Frequency::Weekly);
+    assert_eq!(
+        s.occurrences(date(2024, 3, 1), date(2024, 3, 31)),
+        vec![
+            date(2024, 3, 4),
+            date(2024, 3, 11),
+            date(2024, 3, 18),
+            date(2024, 3, 25)
+        ]
+    );
+}
+
+#[test]
+fn occurrences_before_date_next_are_already_entered() {
+    let s = schedule(date(2024, 3, 1), date(2024, 3, 13), Frequency::Daily);
+    assert_eq!(
+        s.occurrences(date(2024, 3, 10), date(2024, 3, 14)),
+        vec![date(2024, 3, 13), date(2024, 3, 14)]
+    );
+}
+
+#[test]
+fn off_cadence_date_next_is_kept_and_repeats_from_there() {
+    let s = schedule(date(2024, 1, 16), date(2024, 3, 20), Frequency::Monthly);
+    assert_eq!(
+        s.occurrences(date(2024, 3, 1), date(2024, 3, 31)),
+        vec![date(2024, 3, 20)]
+    );
+    assert_eq!(
+        s.occurrences(date(2024, 3, 1), date(2024, 5, 31)),
+        vec![date(2024, 3, 20), date(2024, 4, 20), date(2024, 5, 20)]
+    );
+}
+
+#[test]
+fn clamped_date_next_keeps_the_original_day_of_month() {
+    let s = schedule(date(2024, 1, 31), date(2024, 2, 29), Frequency::Monthly);
+    assert_eq!(
+        s.occurrences(date(2024, 2, 1), date(2024, 3, 31)),
+        vec![date(2024, 2, 29), date(2024, 3, 31)]
+    );
+}
+
+#[test]
+fn monthly_schedules_clamp_to_month_end_without_drifting() {
+    let s = schedule(date(2024, 1, 31), date(2024, 1, 31), Frequency::Monthly);
+    assert_eq!(
+        s.occurrences(date(2024, 1, 1), date(2024, 4, 30)),
+        vec![
+            date(2024, 1, 31),
+            date(2024, 2, 29),
+            date(2024, 3, 31),
+            date(2024, 4, 30)
+        ]
+    );
+}
+
+#[test]
+fn twice_a_month_repeats_fifteen_days_later() {
+    let s = schedule(date(2024, 1, 1), date(2024, 1, 1), Frequency::TwiceAMonth);
+    assert_eq!(
+        s.occurrences(date(2024, 2, 1), date(2024, 3, 10)),
+        vec![date(2024, 2, 1), date(2024, 2, 16), date(2024, 3, 1)]
+    );
+}
+
+#[test]
+fn one_off_schedules_happen_once() {
+    let s = schedule(date(2024, 3, 12), date(2024, 3, 12), Frequency::Never);
+    assert_eq!(
+        s.occurrences(date(2024, 3, 1), date(2024, 12, 31)),
+        vec![date(2024, 3, 12)]
+    );
+    assert!(
+        s.occurrences(date(2024, 3, 13), date(2024, 3, 31))
+            .is_empty()
+    );
+}
+
+#[test]
+fn nth_date_covers_every_frequency() {
+    let first = date(2024, 8, 31);
+    let third: Vec<(Frequency, Option<NaiveDate>)> = [
+        Frequency::Never,
+        Frequency::Daily,
+        Frequency::Weekly,
+        Frequency::EveryOtherWeek,
+        Frequency::TwiceAMonth,
+        Frequency::Every4Weeks,
+        Frequency::Monthly,
+        Frequency::EveryOtherMonth,
+        Frequency::Every3Months,
+        Frequency::Every4Months,
+        Frequency::TwiceAYear,
+        Frequency::Yearly,
+        Frequency::EveryOtherYear,
+    ]
+    .into_iter()
+    .map(|frequency| (frequency, frequency.nth_date(first, 2)))
+    .collect();
+    insta::assert_debug_snapshot!(third);
+}
+
+#[test]
+fn frequencies_use_ynab_names() {
+    let parsed: Vec<Frequency> =
+        serde_json::from_str(r#"["never", "everyOtherWeek", "every4Weeks", "twiceAYear"]"#)
+            .unwrap();
+    assert_eq!(
+        parsed,
+        vec![
+            Frequency::Never,
+            Frequency::EveryOtherWeek,
+            Frequency::Every4Weeks,
+            Frequency::TwiceAYear
+        ]
+    );
+}
@@ -0,0 +1,123 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use crustynab::Error;
+use crustynab::secret::{Secret, TokenSources};
+
+const TOKEN: &str = "very-secret-token";
+
+fn token_file(mode: u32) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "{TOKEN}").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    file
+}
+
+fn command(script: &str) -> Option<Vec<String>> {
+    Some(vec!["sh".into(), "-c".into(), script.into()])
+}
+
+#[test]
+fn inline_token_is_used_without_sources() {
+    let inline = Secret::from("inline");
+    let token = TokenSources::default().resolve(Some(&inline)).unwrap();
+    assert_eq!(token.expose(), "inline");
+}
+
+#[test]
+fn missing_token_is_config_invalid() {
+    let err = TokenSources::default().resolve(None).unwrap_err();
+    assert!(matches!(err, Error::ConfigInvalid(_)));
+}
+
+#[test]
+fn environment_variable_comes_first() {
+    // SAFETY: the variable name is unique to this test
+    unsafe { std::env::set_var("CRUSTYNAB_TEST_TOKEN_FIRST", TOKEN) };
+    let file = token_file(0o600);
+    let sources = TokenSources {
+        env: Some("CRUSTYNAB_TEST_TOKEN_FIRST".into()),
+        file: Some(file.path().to_path_buf()),
+        command: command("echo from-command"),
+    };
+    let token = sources.resolve(Some(&Secret::from("inline"))).unwrap();
+    assert_eq!(token.expose(), TOKEN);
+}
+
+#[test]
+fn unset_environment_variable_falls_through_to_file() {
+    let file = token_file(0o600);
+    let sources = TokenSources {
+        env: Some("CRUSTYNAB_TEST_TOKEN_UNSET".into()),
+        file: Some(file.path().to_path_buf()),
+        command: command("echo from-command"),
+    };
+    let token = sources.resolve(None).unwrap();
+    assert_eq!(token.expose(), TOKEN);
+}
+
+#[test]
+fn unset_environment_variable_without_fallback_is_an_error() {
+    let sources = TokenSources {
+        env: Some("CRUSTYNAB_TEST_TOKEN_ALSO_UNSET".into()),
+        ..TokenSources::default()
+    };
+    let err = sources.resolve(None).unwrap_err();
+    assert!(matches!(err, Error::Token(_)));
+    assert!(err.to_string().contains("CRUSTYNAB_TEST_TOKEN_ALSO_UNSET"));
+}
+
+#[test]
+fn command_output_is_trimmed() {
+    let sources = TokenSources {
+        command: command(&format!("printf '{TOKEN}\\n'")),
+        ..TokenSources::default()
+    };
+    assert_eq!(sources.resolve(None).unwrap().expose(), TOKEN);
+}
+
+#[test]
+fn failing_command_error_does_not_echo_its_output() {
+    let sources = TokenSources {
+        command: command(&format!("echo {TOKEN}; exit 3")),
+        ..TokenSources::default()
+    };
+    let err = sources.resolve(None).unwrap_err();
+    assert!(matches!(err, Error::Token(_)));
+    assert!(!err.to_string().contains(TOKEN));
+}
+
+#[test]
+fn missing_token_file_is_an_error() {
+    let sources = TokenSources {
+        file: Some(PathBuf::from("/nonexistent/crustynab-token")),
+        ..TokenSources::default()
+    };
+    assert!(sources.resolve(Some(&Secret::from("inline"))).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn token_file_readable_by_others_is_rejected() {
+    let file = token_file(0o644);
+    let sources = TokenSources {
+        file: Some(file.path().to_path_buf()),
+        ..TokenSources::default()
+    };
+    let err = sources.resolve(None).unwrap_err();
+    assert!(matches!(err, Error::Token(_)));
+    assert!(err.to_string().contains("mode 644"));
+    assert!(!err.to_string().contains(TOKEN));
+}
+
+#[test]
+fn debug_output_redacts_the_token() {
+    let secret = Secret::from(TOKEN);
+    assert!(!format!("{secret:?}").contains(TOKEN));
+}
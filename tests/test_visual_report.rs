@@ -1,30 +1,83 @@
-use crustynab::visual_report::{CURRENCY, build_visual_report_html, darken_hex, format_currency};
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use crustynab::money::{CurrencyFormat, Milliunits};
+use crustynab::report;
+use crustynab::visual_report::{
+    PlannedYear, ReportSections, build_visual_report_html, darken_hex, format_currency,
+};
+use crustynab::ynab::{Frequency, MonthSummary, ScheduledTransaction};
 use indexmap::IndexMap;
 use polars::prelude::*;
 
 #[test]
 fn format_currency_positive() {
-    insta::assert_snapshot!(format_currency(18.5, true));
+    insta::assert_snapshot!(format_currency(18.5, true, &CurrencyFormat::default()));
 }
 
 #[test]
 fn format_currency_negative() {
-    insta::assert_snapshot!(format_currency(-25.0, true));
+    insta::assert_snapshot!(format_currency(-25.0, true, &CurrencyFormat::default()));
 }
 
 #[test]
 fn format_currency_zero_show() {
-    insta::assert_snapshot!(format_currency(0.0, true));
+    insta::assert_snapshot!(format_currency(0.0, true, &CurrencyFormat::default()));
 }
 
 #[test]
 fn format_currency_zero_hide() {
-    insta::assert_snapshot!(format_currency(0.0, false));
+    insta::assert_snapshot!(format_currency(0.0, false, &CurrencyFormat::default()));
 }
 
 #[test]
 fn format_currency_large_with_commas() {
-    insta::assert_snapshot!(format_currency(1234567.89, true));
+    insta::assert_snapshot!(format_currency(
+        1234567.89,
+        true,
+        &CurrencyFormat::default()
+    ));
+}
+
+fn euro() -> CurrencyFormat {
+    CurrencyFormat {
+        iso_code: "EUR".into(),
+        decimal_digits: 2,
+        decimal_separator: ",".into(),
+        symbol_first: false,
+        group_separator: ".".into(),
+        currency_symbol: "€".into(),
+        display_symbol: true,
+    }
+}
+
+#[test]
+fn format_currency_uses_budget_separators_and_symbol_placement() {
+    assert_eq!(
+        format_currency(-1234567.891, true, &euro()),
+        "-1.234.567,89€"
+    );
+}
+
+#[test]
+fn format_currency_respects_decimal_digits() {
+    let yen = CurrencyFormat {
+        iso_code: "JPY".into(),
+        decimal_digits: 0,
+        currency_symbol: "¥".into(),
+        ..CurrencyFormat::default()
+    };
+    assert_eq!(format_currency(12345.4, true, &yen), "¥12,345");
+    assert_eq!(format_currency(0.4, false, &yen), "");
+}
+
+#[test]
+fn format_currency_can_hide_symbol() {
+    let plain = CurrencyFormat {
+        display_symbol: false,
+        ..euro()
+    };
+    assert_eq!(format_currency(18.5, true, &plain), "18,50");
 }
 
 #[test]
@@ -47,19 +100,21 @@ fn darken_hex_short_passthrough() {
     insta::assert_snapshot!(darken_hex("#fff", 0.85));
 }
 
-fn make_report_lazyframe(rows: Vec<(&str, &str, f64, f64, f64, &str)>) -> LazyFrame {
+fn make_report_lazyframe(rows: Vec<(&str, &str, i64, i64, i64, &str)>) -> LazyFrame {
     let cat_names: Vec<&str> = rows.iter().map(|r| r.0).collect();
     let group_names: Vec<&str> = rows.iter().map(|r| r.1).collect();
-    let budgeted: Vec<f64> = rows.iter().map(|r| r.2).collect();
-    let spent: Vec<f64> = rows.iter().map(|r| r.3).collect();
-    let balance: Vec<f64> = rows.iter().map(|r| r.4).collect();
+    let budgeted: Vec<i64> = rows.iter().map(|r| r.2).collect();
+    let spent: Vec<i64> = rows.iter().map(|r| r.3).collect();
+    let balance: Vec<i64> = rows.iter().map(|r| r.4).collect();
     let cadence: Vec<&str> = rows.iter().map(|r| r.5).collect();
+    let upcoming = vec![0_i64; rows.len()];
 
     DataFrame::new(vec![
         Column::new("category_name".into(), &cat_names),
         Column::new("category_group_name".into(), &group_names),
         Column::new("budgeted".into(), &budgeted),
         Column::new("spent".into(), &spent),
+        Column::new("upcoming".into(), &upcoming),
         Column::new("balance".into(), &balance),
         Column::new("goal_cadence".into(), &cadence),
     ])
@@ -70,10 +125,10 @@ fn make_report_lazyframe(rows: Vec<(&str, &str, f64, f64, f64, &str)>) -> LazyFr
 #[test]
 fn visual_report_basic() {
     let report = make_report_lazyframe(vec![
-        ("Groceries", "Essentials", 50.0, -18.5, 31.5, "monthly"),
-        ("Rent", "Essentials", 100.0, -25.0, 75.0, "annual"),
-        ("Books", "Fun", 10.0, -4.0, 6.0, "annual"),
-        ("Games", "Fun", 20.0, -3.0, 17.0, "annual"),
+        ("Groceries", "Essentials", 50000, -18500, 31500, "monthly"),
+        ("Rent", "Essentials", 100000, -25000, 75000, "annual"),
+        ("Books", "Fun", 10000, -4000, 6000, "annual"),
+        ("Games", "Fun", 20000, -3000, 17000, "annual"),
     ]);
 
     let mut group_colors = IndexMap::new();
@@ -82,10 +137,15 @@ fn visual_report_basic() {
 
     let html = build_visual_report_html(
         report,
+        ReportSections::default(),
         &group_colors,
         "Week 11 (Mar 10 - Mar 16)",
-        2024,
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
         true,
+        &CurrencyFormat::default(),
     )
     .unwrap();
 
@@ -95,39 +155,201 @@ fn visual_report_basic() {
 #[test]
 fn visual_report_totals_include_hidden_balance() {
     let report = make_report_lazyframe(vec![
-        ("Groceries", "Essentials", 50.0, -10.0, 30.0, "monthly"),
-        ("Savings", "Essentials", 20.0, 0.0, 90.0, "monthly"),
+        ("Groceries", "Essentials", 50000, -10000, 30000, "monthly"),
+        ("Savings", "Essentials", 20000, 0, 90000, "monthly"),
     ]);
 
     let mut group_colors = IndexMap::new();
     group_colors.insert("Essentials".to_string(), "#dfe7f5".to_string());
 
-    let html = build_visual_report_html(report, &group_colors, "Week 1", 2024, false).unwrap();
+    let html = build_visual_report_html(
+        report,
+        ReportSections::default(),
+        &group_colors,
+        "Week 1",
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
+        false,
+        &CurrencyFormat::default(),
+    )
+    .unwrap();
 
     assert!(!html.contains("Savings"));
     assert!(html.contains("Total Essentials"));
-    assert!(html.contains(&format!("{CURRENCY}840.00")));
-    assert!(html.contains(&format!("{CURRENCY}70.00")));
-    assert!(html.contains(&format!("{CURRENCY}10.00")));
+    assert!(html.contains("£840.00"));
+    assert!(html.contains("£70.00"));
+    assert!(html.contains("£10.00"));
     insta::assert_snapshot!(html);
 }
 
+#[test]
+fn visual_report_keeps_row_order_and_shows_unwatched_groups() {
+    let report = make_report_lazyframe(vec![
+        ("Rent", "Essentials", 100000, -25000, 75000, "annual"),
+        ("Food", "Essentials", 50000, -18500, 31500, "monthly"),
+        ("Games", "Fun", 20000, -3000, 17000, "annual"),
+    ]);
+
+    let mut group_colors = IndexMap::new();
+    group_colors.insert("Essentials".to_string(), "#dfe7f5".to_string());
+
+    let html = build_visual_report_html(
+        report,
+        ReportSections::default(),
+        &group_colors,
+        "Week 1",
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
+        true,
+        &CurrencyFormat::default(),
+    )
+    .unwrap();
+
+    let rent = html.find(">Rent<").unwrap();
+    let food = html.find(">Food<").unwrap();
+    assert!(rent < food);
+    assert!(html.contains("Total Fun"));
+    assert!(html.contains("#e6e6e6"));
+}
+
 #[test]
 fn visual_report_hides_remaining_when_no_spend() {
     let report = make_report_lazyframe(vec![(
         "Zero Spend",
         "Essentials",
-        50.0,
-        0.0,
-        50.0,
+        50000,
+        0,
+        50000,
         "monthly",
     )]);
 
     let mut group_colors = IndexMap::new();
     group_colors.insert("Essentials".to_string(), "#dfe7f5".to_string());
 
-    let html = build_visual_report_html(report, &group_colors, "Week 1", 2024, true).unwrap();
+    let html = build_visual_report_html(
+        report,
+        ReportSections::default(),
+        &group_colors,
+        "Week 1",
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
+        true,
+        &CurrencyFormat::default(),
+    )
+    .unwrap();
 
     assert!(html.contains("Zero Spend"));
     insta::assert_snapshot!(html);
 }
+
+#[test]
+fn visual_report_lists_upcoming_scheduled_transactions() {
+    let report = make_report_lazyframe(vec![
+        ("Groceries", "Essentials", 50000, -18500, 31500, "monthly"),
+        ("Rent", "Essentials", 100000, 0, 75000, "annual"),
+    ])
+    .with_column(
+        when(col("category_name").eq(lit("Rent")))
+            .then(lit(-80000_i64))
+            .otherwise(col("upcoming"))
+            .alias("upcoming"),
+    );
+    let rent = ScheduledTransaction {
+        id: "s1".into(),
+        date_first: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+        date_next: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+        frequency: Frequency::Monthly,
+        amount: Milliunits(-80000),
+        memo: None,
+        flag_color: None,
+        account_id: "a1".into(),
+        account_name: "Checking".into(),
+        payee_name: Some("Landlord & Sons".into()),
+        category_name: Some("Rent".into()),
+        transfer_account_id: None,
+        subtransactions: vec![],
+        deleted: false,
+    };
+    let upcoming = report::upcoming_transactions_to_polars(
+        &[rent],
+        &[],
+        NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 3, 16).unwrap(),
+    )
+    .unwrap();
+    let names: HashSet<String> = ["Groceries".to_string(), "Rent".to_string()].into();
+
+    let mut group_colors = IndexMap::new();
+    group_colors.insert("Essentials".to_string(), "#dfe7f5".to_string());
+
+    let html = build_visual_report_html(
+        report,
+        ReportSections {
+            upcoming: Some(report::build_upcoming_table(upcoming, &names)),
+            ..Default::default()
+        },
+        &group_colors,
+        "Week 11 (Mar 10 - Mar 16)",
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
+        false,
+        &CurrencyFormat::default(),
+    )
+    .unwrap();
+
+    assert!(html.contains("Rent"));
+    assert!(html.contains("Landlord &amp; Sons"));
+    insta::assert_snapshot!(html);
+}
+
+#[test]
+fn visual_report_starts_with_budget_month_summary() {
+    let report = make_report_lazyframe(vec![(
+        "Groceries",
+        "Essentials",
+        50000,
+        -18500,
+        31500,
+        "monthly",
+    )]);
+    let summary = MonthSummary {
+        month: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        income: Milliunits(3200000),
+        budgeted: Milliunits(2950000),
+        activity: Milliunits(-1184250),
+        to_be_budgeted: Milliunits(250000),
+        age_of_money: Some(42),
+    };
+
+    let mut group_colors = IndexMap::new();
+    group_colors.insert("Essentials".to_string(), "#dfe7f5".to_string());
+
+    let html = build_visual_report_html(
+        report,
+        ReportSections {
+            month_summary: Some(report::build_month_summary_table(&summary).unwrap()),
+            ..Default::default()
+        },
+        &group_colors,
+        "Week 11 (Mar 10 - Mar 16)",
+        PlannedYear {
+            year: 2024,
+            budgeted_months: 1,
+        },
+        true,
+        &CurrencyFormat::default(),
+    )
+    .unwrap();
+
+    assert!(html.contains("Budget for March 2024"));
+    assert!(html.contains("42 days"));
+    insta::assert_snapshot!(html);
+}
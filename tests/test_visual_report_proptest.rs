@@ -1,4 +1,5 @@
-use crustynab::visual_report::{CURRENCY, darken_hex, format_currency};
+use crustynab::money::CurrencyFormat;
+use crustynab::visual_report::{darken_hex, format_currency};
 use proptest::prelude::*;
 
 fn is_valid_hex_color(value: &str) -> bool {
@@ -16,7 +17,8 @@ proptest! {
         show_zero in any::<bool>(),
     ) {
         let rounded = (value * 100.0).round() / 100.0;
-        let formatted = format_currency(value, show_zero);
+        let currency = CurrencyFormat::default();
+        let formatted = format_currency(value, show_zero, &currency);
 
         if rounded == 0.0 && !show_zero {
             prop_assert!(formatted.is_empty());
@@ -24,7 +26,7 @@ proptest! {
         }
 
         prop_assert!(!formatted.is_empty());
-        prop_assert!(formatted.contains(CURRENCY));
+        prop_assert!(formatted.contains(&currency.currency_symbol));
 
         if rounded < 0.0 {
             prop_assert!(formatted.starts_with('-'));
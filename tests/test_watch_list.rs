@@ -0,0 +1,89 @@
+use crustynab::ynab::CategoryGroup;
+use crustynab::{report, watch_list};
+use indexmap::IndexMap;
+
+fn group(id: &str, name: &str) -> CategoryGroup {
+    CategoryGroup {
+        id: id.into(),
+        name: name.into(),
+        hidden: false,
+        deleted: false,
+        categories: vec![],
+    }
+}
+
+fn make_groups() -> Vec<CategoryGroup> {
+    vec![
+        group("g1", "🏠 Essentials"),
+        group("g2", "Fun Money"),
+        group("g3", "Bills: Monthly"),
+        group("g4", "Bills: Yearly"),
+    ]
+}
+
+fn watch(keys: &[&str]) -> IndexMap<String, String> {
+    keys.iter()
+        .enumerate()
+        .map(|(i, key)| (key.to_string(), format!("#00000{i}")))
+        .collect()
+}
+
+#[test]
+fn names_match_ignoring_case_emoji_and_spaces() {
+    assert_eq!(watch_list::normalize_name("🏠  Essentials "), "essentials");
+    let resolved =
+        watch_list::resolve(&make_groups(), &watch(&["ESSENTIALS", "fun  money"])).unwrap();
+    let names: Vec<&str> = resolved.keys().map(String::as_str).collect();
+    assert_eq!(names, vec!["🏠 Essentials", "Fun Money"]);
+}
+
+#[test]
+fn ids_globs_and_regexes_match_groups() {
+    let groups = make_groups();
+    let resolved =
+        watch_list::resolve(&groups, &watch(&["id:g2", "glob:bills*", "re:^🏠"])).unwrap();
+    insta::assert_snapshot!(format!("{resolved:?}"));
+}
+
+#[test]
+fn first_matching_key_sets_the_colour() {
+    let resolved =
+        watch_list::resolve(&make_groups(), &watch(&["re:Monthly$", "glob:Bills*"])).unwrap();
+    assert_eq!(resolved["Bills: Monthly"], "#000000");
+    assert_eq!(resolved["Bills: Yearly"], "#000001");
+}
+
+#[test]
+fn missing_keys_are_those_matching_nothing() {
+    let groups = make_groups();
+    let missing =
+        report::get_missing_category_groups(&groups, &watch(&["essentials", "id:g9", "Fun"]));
+    let mut missing: Vec<&str> = missing.iter().map(String::as_str).collect();
+    missing.sort();
+    assert_eq!(missing, vec!["Fun", "id:g9"]);
+}
+
+#[test]
+fn missing_names_get_did_you_mean_suggestions() {
+    let groups = make_groups();
+    assert_eq!(
+        watch_list::suggestions(&groups, "Fun"),
+        vec!["Fun Money".to_string()]
+    );
+    assert_eq!(
+        watch_list::suggestions(&groups, "Esentials"),
+        vec!["🏠 Essentials".to_string()]
+    );
+    assert_eq!(
+        watch_list::suggestions(&[group("g5", "Fun")], "Fnu"),
+        vec!["Fun".to_string()]
+    );
+    assert!(watch_list::suggestions(&groups, "Holidays").is_empty());
+    assert!(watch_list::suggestions(&groups, "id:g9").is_empty());
+}
+
+#[test]
+fn bad_patterns_are_config_invalid() {
+    let err = watch_list::resolve(&make_groups(), &watch(&["re:("])).unwrap_err();
+    assert!(matches!(err, crustynab::Error::ConfigInvalid(_)));
+}